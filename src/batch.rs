@@ -0,0 +1,499 @@
+use crate::codes::*;
+use crate::preprocess::*;
+use crate::solver::*;
+use crate::weights::*;
+use mwpf::mwpf_solver::SolverTrait;
+use mwpf::util::*;
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// the number of shots decoded by a worker at a time, whose results are streamed back together
+const BATCH_CHUNK_SHOTS: usize = 256;
+
+/// the formats of a batch of shots, where the Stim result formats list the detectors of each shot on the way in
+/// and the predicted observable flips on the way out, as `stim sample_dets` and `--obs_out` do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShotFormat {
+    /// an array of the defect vertices of each shot, e.g. [[0, 1], []], answered by an array of results
+    Json,
+    /// a line of '0' and '1' per shot
+    #[serde(rename = "01")]
+    Zero1,
+    /// the bits of each shot packed into whole bytes, little endian
+    B8,
+    /// the number of zeros before each one as a byte, with an implicit one after the end of each shot;
+    /// 255 stands for 255 zeros without a one
+    R8,
+    /// a line "shot D0 D5" per shot, or "shot L1" for the observables
+    Dets,
+}
+
+/// the decoding result of a single shot in a batch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShotResult {
+    pub correction: Vec<(usize, String)>,
+    pub correction_edges: Vec<usize>,
+    /// the logical observables flipped by the correction
+    pub observables: Vec<usize>,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl ShotFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ShotFormat::Json => "application/json",
+            ShotFormat::Zero1 | ShotFormat::Dets => "text/plain",
+            ShotFormat::B8 | ShotFormat::R8 => "application/octet-stream",
+        }
+    }
+
+    /// parse the defect vertices of each shot among `width` detectors; the observables appended to the
+    /// detectors of a `dets` shot are ignored
+    pub fn parse_shots(&self, content: &[u8], width: usize) -> Result<Vec<Vec<usize>>, String> {
+        let check = |shot_idx: usize, shot: &[usize]| -> Result<(), String> {
+            match shot.iter().find(|&&vertex| vertex >= width) {
+                Some(vertex) => Err(format!(
+                    "detector {} of shot {} out of range",
+                    vertex, shot_idx
+                )),
+                None => Ok(()),
+            }
+        };
+        let text = || {
+            std::str::from_utf8(content).map_err(|_| format!("the {:?} shots are not UTF-8", self))
+        };
+        match self {
+            ShotFormat::Json => {
+                let shots: Vec<Vec<usize>> = serde_json::from_slice(content)
+                    .map_err(|error| format!("invalid JSON shots: {}", error))?;
+                for (shot_idx, shot) in shots.iter().enumerate() {
+                    check(shot_idx, shot)?;
+                }
+                Ok(shots)
+            }
+            ShotFormat::Zero1 => text()?
+                .lines()
+                .enumerate()
+                .map(|(shot_idx, line)| -> Result<Vec<usize>, String> {
+                    let line = line.trim_end_matches('\r');
+                    if line.len() != width {
+                        return Err(format!(
+                            "shot {} has {} bits instead of {}",
+                            shot_idx,
+                            line.len(),
+                            width
+                        ));
+                    }
+                    line.chars()
+                        .enumerate()
+                        .filter(|(_, bit)| *bit != '0')
+                        .map(|(vertex, bit)| match bit {
+                            '1' => Ok(vertex),
+                            _ => Err(format!("invalid bit {:?} in shot {}", bit, shot_idx)),
+                        })
+                        .collect()
+                })
+                .collect(),
+            ShotFormat::B8 => {
+                let shot_bytes = width.div_ceil(8);
+                if shot_bytes == 0 || content.len() % shot_bytes != 0 {
+                    return Err(format!(
+                        "{} bytes are not whole shots of {} detectors",
+                        content.len(),
+                        width
+                    ));
+                }
+                content
+                    .chunks(shot_bytes)
+                    .enumerate()
+                    .map(|(shot_idx, bytes)| {
+                        let shot: Vec<usize> = (0..shot_bytes * 8)
+                            .filter(|bit| bytes[bit / 8] & (1 << (bit % 8)) != 0)
+                            .collect();
+                        check(shot_idx, &shot)?;
+                        Ok(shot)
+                    })
+                    .collect()
+            }
+            ShotFormat::R8 => {
+                let mut shots = vec![];
+                let mut shot = vec![];
+                let mut position = 0;
+                for &run in content.iter() {
+                    position += run as usize;
+                    if run == 255 {
+                        continue;
+                    }
+                    if position == width {
+                        shots.push(std::mem::take(&mut shot));
+                        position = 0;
+                        continue;
+                    }
+                    check(shots.len(), &[position])?;
+                    shot.push(position);
+                    position += 1;
+                }
+                if position != 0 || !shot.is_empty() {
+                    return Err("the last r8 shot is not terminated".to_string());
+                }
+                Ok(shots)
+            }
+            ShotFormat::Dets => text()?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .enumerate()
+                .map(|(shot_idx, line)| {
+                    let mut targets = line.split_whitespace();
+                    if targets.next() != Some("shot") {
+                        return Err(format!("shot {} does not start with \"shot\"", shot_idx));
+                    }
+                    let mut shot = vec![];
+                    for target in targets {
+                        let invalid = || format!("invalid target {} in shot {}", target, shot_idx);
+                        match target.split_at_checked(1) {
+                            Some(("D", index)) => {
+                                shot.push(index.parse::<usize>().map_err(|_| invalid())?)
+                            }
+                            Some(("L", index)) if index.parse::<usize>().is_ok() => {}
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    shot.sort();
+                    check(shot_idx, &shot)?;
+                    Ok(shot)
+                })
+                .collect(),
+        }
+    }
+
+    /// write the results of consecutive shots starting from `first_shot`, as the predicted flips of
+    /// `observable_num` observables for the Stim formats
+    pub fn write_results(
+        &self,
+        results: &[ShotResult],
+        first_shot: usize,
+        observable_num: usize,
+        output: &mut Vec<u8>,
+    ) {
+        for (shot_idx, result) in results.iter().enumerate() {
+            let observables = &result.observables;
+            match self {
+                ShotFormat::Json => {
+                    if first_shot + shot_idx > 0 {
+                        output.push(b',');
+                    }
+                    serde_json::to_writer(&mut *output, result).unwrap();
+                }
+                ShotFormat::Zero1 => {
+                    let mut line = vec![b'0'; observable_num];
+                    for &observable in observables.iter() {
+                        line[observable] = b'1';
+                    }
+                    output.extend(line);
+                    output.push(b'\n');
+                }
+                ShotFormat::B8 => {
+                    let mut bytes = vec![0u8; observable_num.div_ceil(8)];
+                    for &observable in observables.iter() {
+                        bytes[observable / 8] |= 1 << (observable % 8);
+                    }
+                    output.extend(bytes);
+                }
+                ShotFormat::R8 => {
+                    let mut previous = 0;
+                    for &position in observables.iter().chain(std::iter::once(&observable_num)) {
+                        let mut zeros = position - previous;
+                        while zeros >= 255 {
+                            output.push(255);
+                            zeros -= 255;
+                        }
+                        output.push(zeros as u8);
+                        previous = position + 1;
+                    }
+                }
+                ShotFormat::Dets => {
+                    output.extend(b"shot");
+                    for &observable in observables.iter() {
+                        output.extend(format!(" L{}", observable).bytes());
+                    }
+                    output.push(b'\n');
+                }
+            }
+        }
+    }
+}
+
+/// the solvers of a decoding thread, built at their first use and cleared after each shot
+#[derive(Default)]
+pub struct ShotSolvers {
+    solver: Option<Box<dyn SolverTrait>>,
+    /// the solvers of the components of a preprocessed graph
+    component_solvers: HashMap<usize, Box<dyn SolverTrait>>,
+}
+
+/// decodes many shots of a code with the same weights
+pub struct BatchDecoder {
+    pub code: Arc<ServerCodeInfo>,
+    initializer: Arc<SolverInitializer>,
+    preprocessed: Option<PreprocessedGraph>,
+    solver_options: SolverOptions,
+    edge_observables: Vec<Vec<usize>>,
+}
+
+impl BatchDecoder {
+    pub fn new(
+        code: Arc<ServerCodeInfo>,
+        overrides: &WeightOverrides,
+        solver_options: SolverOptions,
+        with_preprocess: bool,
+    ) -> Result<Self, String> {
+        let initializer = code.reweighted_initializer(overrides)?;
+        let preprocessed = with_preprocess.then(|| preprocess(&initializer));
+        let edge_observables = (0..code.edge_events.len())
+            .map(|edge_index| code.edge_observables(edge_index))
+            .collect();
+        Ok(Self {
+            code,
+            initializer: Arc::new(initializer),
+            preprocessed,
+            solver_options,
+            edge_observables,
+        })
+    }
+
+    pub fn decode(&self, syndrome: &[usize]) -> ShotResult {
+        self.decode_reusing(syndrome, &mut ShotSolvers::default())
+    }
+
+    /// [`Self::decode`] with the solvers of the calling thread, which are reused across its shots
+    pub fn decode_reusing(&self, syndrome: &[usize], solvers: &mut ShotSolvers) -> ShotResult {
+        let (mut subgraph, weight_range) = match &self.preprocessed {
+            Some(graph) => graph.decode_reusing(
+                syndrome,
+                &self.solver_options,
+                &mut solvers.component_solvers,
+            ),
+            None => {
+                let solver = solvers
+                    .solver
+                    .get_or_insert_with(|| self.solver_options.build(&self.initializer));
+                solver.solve_visualizer(SyndromePattern::new_vertices(syndrome.to_vec()), None);
+                let result = solver.subgraph_range_visualizer(None);
+                solver.clear();
+                result
+            }
+        };
+        subgraph.sort();
+        let mut observables = vec![false; self.code.client_info.logical_observables.len()];
+        for &edge_index in subgraph.iter() {
+            for &observable in self.edge_observables[edge_index].iter() {
+                observables[observable] = !observables[observable];
+            }
+        }
+        ShotResult {
+            correction: subgraph
+                .iter()
+                .map(|edge_index| self.code.edge_errors[*edge_index].clone())
+                .collect(),
+            observables: (0..observables.len())
+                .filter(|observable| observables[*observable])
+                .collect(),
+            correction_edges: subgraph,
+            lower: weight_range.lower.to_f64().unwrap(),
+            upper: weight_range.upper.to_f64().unwrap(),
+        }
+    }
+
+    /// decode the shots in chunks on a pool of `worker_num` threads and pass the output of the chunks in order to
+    /// `send`, which stops the decoding by returning false
+    pub fn decode_stream(
+        &self,
+        shots: &[Vec<usize>],
+        format: ShotFormat,
+        worker_num: usize,
+        mut send: impl FnMut(Vec<u8>) -> bool,
+    ) {
+        let observable_num = self.code.client_info.logical_observables.len();
+        if format == ShotFormat::Json && !send(b"[".to_vec()) {
+            return;
+        }
+        let chunks: Vec<&[Vec<usize>]> = shots.chunks(BATCH_CHUNK_SHOTS).collect();
+        let next_chunk = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let finished = std::thread::scope(|scope| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            for _ in 0..worker_num.max(1) {
+                let sender = sender.clone();
+                let (chunks, next_chunk, stopped) = (&chunks, &next_chunk, &stopped);
+                scope.spawn(move || {
+                    let mut solvers = ShotSolvers::default();
+                    while !stopped.load(Ordering::Relaxed) {
+                        let chunk_idx = next_chunk.fetch_add(1, Ordering::Relaxed);
+                        let Some(chunk) = chunks.get(chunk_idx) else {
+                            break;
+                        };
+                        let results: Vec<ShotResult> = chunk
+                            .iter()
+                            .map(|syndrome| self.decode_reusing(syndrome, &mut solvers))
+                            .collect();
+                        let mut output = vec![];
+                        format.write_results(
+                            &results,
+                            chunk_idx * BATCH_CHUNK_SHOTS,
+                            observable_num,
+                            &mut output,
+                        );
+                        if sender.send((chunk_idx, output)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+            // the chunks finish out of order, so they wait until the previous ones are sent
+            let mut pending = BTreeMap::new();
+            let mut next_output = 0;
+            for (chunk_idx, output) in receiver {
+                pending.insert(chunk_idx, output);
+                while let Some(output) = pending.remove(&next_output) {
+                    next_output += 1;
+                    if !send(output) {
+                        stopped.store(true, Ordering::Relaxed);
+                        return false;
+                    }
+                }
+            }
+            true
+        });
+        if finished && format == ShotFormat::Json {
+            send(b"]".to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shot_formats() {
+        // cargo test -- test_shot_formats --nocapture
+        let width = 300;
+        let shots = vec![vec![], vec![0, 7, 8, 299], vec![1, 2, 260]];
+        let results: Vec<ShotResult> = shots
+            .iter()
+            .map(|shot| ShotResult {
+                correction: vec![],
+                correction_edges: vec![],
+                observables: shot.clone(),
+                lower: 0.0,
+                upper: 0.0,
+            })
+            .collect();
+        for format in [
+            ShotFormat::Zero1,
+            ShotFormat::B8,
+            ShotFormat::R8,
+            ShotFormat::Dets,
+        ] {
+            let mut output = vec![];
+            format.write_results(&results[..1], 0, width, &mut output);
+            format.write_results(&results[1..], 1, width, &mut output);
+            let dets = String::from_utf8_lossy(&output).replace('L', "D");
+            let content = match format {
+                ShotFormat::Dets => dets.as_bytes(),
+                _ => &output,
+            };
+            assert_eq!(format.parse_shots(content, width).unwrap(), shots);
+            assert!(format.parse_shots(content, 200).is_err());
+        }
+        let mut output = vec![];
+        ShotFormat::R8.write_results(&results[1..2], 0, width, &mut output);
+        assert_eq!(output, vec![0, 6, 0, 255, 35, 0]);
+        assert_eq!(
+            ShotFormat::Dets
+                .parse_shots(b"shot D3 L0 D1\n\nshot\n", 4)
+                .unwrap(),
+            vec![vec![1, 3], vec![]]
+        );
+        assert!(ShotFormat::Dets.parse_shots(b"shot M1\n", 4).is_err());
+        assert!(ShotFormat::Zero1.parse_shots(b"0120\n", 4).is_err());
+        assert!(ShotFormat::B8.parse_shots(&[0, 0, 0], 9).is_err());
+        assert!(ShotFormat::R8.parse_shots(&[1], 4).is_err());
+        assert_eq!(
+            ShotFormat::Json.parse_shots(b"[[0, 1], []]", 4).unwrap(),
+            vec![vec![0, 1], vec![]]
+        );
+        assert!(ShotFormat::Json.parse_shots(b"[[4]]", 4).is_err());
+    }
+
+    #[test]
+    fn test_batch_decoding() {
+        // cargo test -- test_batch_decoding --nocapture
+        let code = Arc::new(ServerCodeInfo::from(&RotatedSurfaceCode::new(
+            5,
+            NoiseType::BitFlip,
+        )));
+        let observable_num = code.client_info.logical_observables.len();
+        // every single error is corrected, so the predicted flips are those of the error
+        let edge_num = code.solver_initializer.weighted_edges.len();
+        let shots: Vec<Vec<usize>> = (0..1000)
+            .map(|shot_idx| {
+                code.solver_initializer.weighted_edges[shot_idx % edge_num]
+                    .vertices
+                    .clone()
+            })
+            .collect();
+        let expected: Vec<Vec<usize>> = (0..1000)
+            .map(|shot_idx| code.edge_observables(shot_idx % edge_num))
+            .collect();
+        for with_preprocess in [false, true] {
+            let decoder = BatchDecoder::new(
+                code.clone(),
+                &WeightOverrides::default(),
+                SolverOptions::default(),
+                with_preprocess,
+            )
+            .unwrap();
+            let mut output = vec![];
+            decoder.decode_stream(&shots, ShotFormat::Zero1, 3, |chunk| {
+                output.extend(chunk);
+                true
+            });
+            assert_eq!(
+                ShotFormat::Zero1
+                    .parse_shots(&output, observable_num)
+                    .unwrap(),
+                expected
+            );
+            let mut output = vec![];
+            decoder.decode_stream(&shots[..3], ShotFormat::Json, 2, |chunk| {
+                output.extend(chunk);
+                true
+            });
+            let results: Vec<serde_json::Value> = serde_json::from_slice(&output).unwrap();
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[1]["correction_edges"], json!([1]));
+        }
+        // stop early
+        let decoder = BatchDecoder::new(
+            code,
+            &WeightOverrides::default(),
+            SolverOptions::default(),
+            false,
+        )
+        .unwrap();
+        let mut chunk_num = 0;
+        decoder.decode_stream(&shots, ShotFormat::B8, 2, |_| {
+            chunk_num += 1;
+            false
+        });
+        assert_eq!(chunk_num, 1);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug)]
+struct CacheInner<V> {
+    /// the value and the time of its last use
+    entries: HashMap<String, (V, u64)>,
+    clock: u64,
+}
+
+/// an in-memory cache of at most `capacity` values, evicting the least recently used one when full;
+/// unlike [`crate::registry::CodeRegistry`], it is never persisted
+#[derive(Debug)]
+pub struct BoundedCache<V> {
+    inner: Mutex<CacheInner<V>>,
+    capacity: usize,
+}
+
+impl<V: Clone> BoundedCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let (value, last_used) = inner.entries.get_mut(key)?;
+        *last_used = clock;
+        Some(value.clone())
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let lru_key = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            inner.entries.remove(&lru_key);
+        }
+        inner.entries.insert(key, (value, clock));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_cache() {
+        // cargo test -- test_bounded_cache --nocapture
+        let cache = BoundedCache::new(2);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        assert_eq!(cache.get("a"), Some(1));
+        // the least recently used one is evicted
+        cache.insert("c".to_string(), 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("c"), Some(3));
+        // replacing a value does not evict
+        cache.insert("c".to_string(), 4);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("c"), Some(4));
+    }
+}
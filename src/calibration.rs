@@ -0,0 +1,395 @@
+use crate::codes::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// the calibration of a physical qubit, with times in microseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QubitCalibration {
+    pub t1: f64,
+    pub t2: f64,
+    /// the probability of misreading the qubit, in [0, 0.5]; it is validated but does not change the data qubit
+    /// channels, since the calibrated code has no measurement to flip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readout_error: Option<f64>,
+}
+
+/// the calibration of a two-qubit gate applied once per cycle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateCalibration {
+    pub qubits: (usize, usize),
+    /// the average gate fidelity
+    pub fidelity: f64,
+}
+
+/// a snapshot of the calibration of a device, where the qubits are the data qubits of the code in order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCalibration {
+    /// the duration of a syndrome extraction cycle in microseconds, during which the qubits idle
+    pub cycle_time: f64,
+    pub qubits: Vec<QubitCalibration>,
+    #[serde(default)]
+    pub two_qubit_gates: Vec<GateCalibration>,
+}
+
+impl PauliChannel {
+    /// the identity channel
+    pub fn noiseless() -> Self {
+        Self {
+            px: 0.0,
+            py: 0.0,
+            pz: 0.0,
+        }
+    }
+
+    /// the channel of applying `self` and then `other`, multiplying the Pauli errors up to phases
+    pub fn compose(&self, other: &Self) -> Self {
+        let (pi_1, pi_2) = (
+            1.0 - self.px - self.py - self.pz,
+            1.0 - other.px - other.py - other.pz,
+        );
+        Self {
+            px: self.px * pi_2 + pi_1 * other.px + self.py * other.pz + self.pz * other.py,
+            py: self.py * pi_2 + pi_1 * other.py + self.px * other.pz + self.pz * other.px,
+            pz: self.pz * pi_2 + pi_1 * other.pz + self.px * other.py + self.py * other.px,
+        }
+    }
+
+    /// the Pauli twirling approximation of amplitude and phase damping while idling for `time`:
+    /// px = py = (1 - exp(-t/T1)) / 4 and pz = (1 - exp(-t/T2)) / 2 - px
+    pub fn idle(time: f64, t1: f64, t2: f64) -> Self {
+        let px = (1.0 - (-time / t1).exp()) / 4.0;
+        let pz = (1.0 - (-time / t2).exp()) / 2.0 - px;
+        Self {
+            px,
+            py: px,
+            pz: pz.max(0.0),
+        }
+    }
+}
+
+impl DeviceCalibration {
+    pub fn parse_json(content: &str) -> Result<Self, String> {
+        let calibration: Self = serde_json::from_str(content)
+            .map_err(|error| format!("invalid calibration JSON: {}", error))?;
+        calibration.check()?;
+        Ok(calibration)
+    }
+
+    /// parse the CSV format with one record per line, ignoring empty lines and comments after `#`:
+    /// `cycle_time,<us>`, `qubit,<index>,<T1 us>,<T2 us>[,<readout error>]` and `gate,<a>,<b>,<fidelity>`
+    pub fn parse_csv(content: &str) -> Result<Self, String> {
+        let mut cycle_time = None;
+        // the indices come from the client, so they are only checked to be dense after parsing all the lines
+        let mut qubits: BTreeMap<usize, QubitCalibration> = BTreeMap::new();
+        let mut two_qubit_gates = vec![];
+        for (line_idx, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let invalid = || format!("invalid calibration at line {}: {}", line_idx + 1, line);
+            let number = |idx: usize| -> Result<f64, String> {
+                fields
+                    .get(idx)
+                    .and_then(|field| field.parse::<f64>().ok())
+                    .ok_or_else(invalid)
+            };
+            let index = |idx: usize| -> Result<usize, String> {
+                fields
+                    .get(idx)
+                    .and_then(|field| field.parse::<usize>().ok())
+                    .ok_or_else(invalid)
+            };
+            match (fields[0], fields.len()) {
+                ("cycle_time", 2) => cycle_time = Some(number(1)?),
+                ("qubit", 4 | 5) => {
+                    let readout_error = if fields.len() == 5 {
+                        Some(number(4)?)
+                    } else {
+                        None
+                    };
+                    qubits.insert(
+                        index(1)?,
+                        QubitCalibration {
+                            t1: number(2)?,
+                            t2: number(3)?,
+                            readout_error,
+                        },
+                    );
+                }
+                ("gate", 4) => two_qubit_gates.push(GateCalibration {
+                    qubits: (index(1)?, index(2)?),
+                    fidelity: number(3)?,
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+        let calibration = Self {
+            cycle_time: cycle_time.ok_or("missing cycle_time")?,
+            qubits: qubits
+                .into_iter()
+                .enumerate()
+                .map(|(qubit_idx, (index, qubit))| {
+                    if index == qubit_idx {
+                        Ok(qubit)
+                    } else {
+                        Err(format!("missing calibration of qubit {}", qubit_idx))
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+            two_qubit_gates,
+        };
+        calibration.check()?;
+        Ok(calibration)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if !self.cycle_time.is_finite() || self.cycle_time < 0.0 {
+            return Err(format!("invalid cycle time {}", self.cycle_time));
+        }
+        for (qubit_idx, qubit) in self.qubits.iter().enumerate() {
+            if !(qubit.t1 > 0.0 && qubit.t2 > 0.0) {
+                return Err(format!(
+                    "T1 = {} and T2 = {} of qubit {} must be positive",
+                    qubit.t1, qubit.t2, qubit_idx
+                ));
+            }
+            if let Some(readout_error) = qubit
+                .readout_error
+                .filter(|readout_error| !(0.0..=0.5).contains(readout_error))
+            {
+                return Err(format!(
+                    "readout error {} of qubit {} is not in [0, 0.5]",
+                    readout_error, qubit_idx
+                ));
+            }
+        }
+        for gate in self.two_qubit_gates.iter() {
+            let (a, b) = gate.qubits;
+            if a == b || a >= self.qubits.len() || b >= self.qubits.len() {
+                return Err(format!("invalid two-qubit gate on qubits {} and {}", a, b));
+            }
+            if !(0.0..=1.0).contains(&gate.fidelity) {
+                return Err(format!(
+                    "fidelity {} of the gate on qubits {} and {} is not in [0, 1]",
+                    gate.fidelity, a, b
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// the Pauli channel of each qubit per cycle, composing the idling noise and the two-qubit gates as depolarizing
+    /// channels. A two-qubit depolarizing channel of average fidelity F has a random non-identity Pauli with
+    /// probability 5 (1 - F) / 4, 4 of the 15 of which are X (Y, Z) on each qubit; the gates are between data
+    /// qubits, as the ancillas are not part of the code
+    pub fn pauli_channels(&self) -> Vec<PauliChannel> {
+        let mut channels: Vec<PauliChannel> = self
+            .qubits
+            .iter()
+            .map(|qubit| PauliChannel::idle(self.cycle_time, qubit.t1, qubit.t2))
+            .collect();
+        for gate in self.two_qubit_gates.iter() {
+            let p = (5.0 * (1.0 - gate.fidelity) / 4.0).min(1.0) * 4.0 / 15.0;
+            let depolarize = PauliChannel {
+                px: p,
+                py: p,
+                pz: p,
+            };
+            for qubit_idx in [gate.qubits.0, gate.qubits.1] {
+                channels[qubit_idx] = channels[qubit_idx].compose(&depolarize);
+            }
+        }
+        channels
+    }
+}
+
+impl ServerCodeInfo {
+    /// a variant of the code whose errors have the probabilities of the calibrated device
+    pub fn calibrated(
+        &self,
+        calibration: &DeviceCalibration,
+        id: &str,
+    ) -> Result<ServerCodeInfo, String> {
+        let data_qubit_num = self.client_info.data_qubit_actions.len();
+        if calibration.qubits.len() != data_qubit_num {
+            return Err(format!(
+                "calibration of {} qubits for {} data qubits",
+                calibration.qubits.len(),
+                data_qubit_num
+            ));
+        }
+        let mut client_info = self.client_info.clone();
+        client_info.id = id.to_string();
+        client_info.name = format!("{} (Calibrated)", self.client_info.name);
+        let mut checks_on_qubit: Vec<Vec<(usize, &str)>> = vec![vec![]; data_qubit_num];
+        for (stabilizer_idx, check) in self.client_info.stabilizer_checks.iter().enumerate() {
+            for (data_index, check_type) in check.iter() {
+                checks_on_qubit[*data_index].push((stabilizer_idx, check_type.as_str()));
+            }
+        }
+        client_info.error_probabilities = calibration
+            .pauli_channels()
+            .iter()
+            .zip(self.client_info.data_qubit_actions.iter())
+            .zip(checks_on_qubit.iter())
+            .map(|((channel, actions), checks)| {
+                let mut probabilities: HashMap<String, f64> = actions
+                    .keys()
+                    .map(|error_type| (error_type.clone(), channel.probability(error_type)))
+                    .collect();
+                // a Pauli that the code does not list is marginalised onto the listed error of the same syndrome,
+                // e.g. Y onto X for a code with only Z checks, or onto both X and Z that make it up
+                for pauli in ["X", "Y", "Z"] {
+                    if actions.contains_key(pauli) {
+                        continue;
+                    }
+                    let mut syndrome: Vec<usize> = checks
+                        .iter()
+                        .filter(|(_, check_type)| pauli_anticommute(pauli, check_type))
+                        .map(|(stabilizer_idx, _)| *stabilizer_idx)
+                        .collect();
+                    syndrome.sort();
+                    if syndrome.is_empty() {
+                        continue; // undetectable
+                    }
+                    let same_syndrome = actions.iter().find(|(_, error_syndrome)| {
+                        let mut error_syndrome = (*error_syndrome).clone();
+                        error_syndrome.sort();
+                        error_syndrome == syndrome
+                    });
+                    let targets: Vec<&str> = match same_syndrome {
+                        Some((error_type, _)) => vec![error_type.as_str()],
+                        None if pauli == "Y" => ["X", "Z"]
+                            .into_iter()
+                            .filter(|error_type| actions.contains_key(*error_type))
+                            .collect(),
+                        None => vec![],
+                    };
+                    for error_type in targets {
+                        *probabilities.get_mut(error_type).unwrap() += channel.probability(pauli);
+                    }
+                }
+                probabilities
+            })
+            .collect();
+        client_info.check_consistency()?;
+        Ok(ServerCodeInfo::from_client_info(client_info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pauli_channel_compose() {
+        // cargo test -- test_pauli_channel_compose --nocapture
+        let a = PauliChannel::new(0.1, 0.02, 0.03).unwrap();
+        assert_eq!(a.compose(&PauliChannel::noiseless()), a);
+        // two X flips cancel out
+        let x = PauliChannel::new(0.1, 0.0, 0.0).unwrap();
+        let xx = x.compose(&x);
+        assert!((xx.px - 2.0 * 0.1 * 0.9).abs() < 1e-12);
+        // X then Z is Y
+        let z = PauliChannel::new(0.0, 0.0, 0.2).unwrap();
+        assert!((x.compose(&z).py - 0.1 * 0.2).abs() < 1e-12);
+        // pure dephasing when T1 is infinite
+        let idle = PauliChannel::idle(1.0, f64::INFINITY, 10.0);
+        assert_eq!(idle.px, 0.0);
+        assert!((idle.pz - (1.0 - (-0.1f64).exp()) / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_calibrated_code() {
+        // cargo test -- test_calibrated_code --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        let mut csv = "# a snapshot of the chip\ncycle_time, 1.0\n".to_string();
+        for qubit_idx in 0..9 {
+            csv += &format!(
+                "qubit,{},{},{}\n",
+                qubit_idx,
+                100.0,
+                80.0 + qubit_idx as f64
+            );
+        }
+        csv += "gate,0,1,0.99\ngate,4,5,0.995\n";
+        let calibration = DeviceCalibration::parse_csv(&csv).unwrap();
+        let json = serde_json::to_string(&calibration).unwrap();
+        assert_eq!(
+            serde_json::to_string(&DeviceCalibration::parse_json(&json).unwrap()).unwrap(),
+            json
+        );
+        let calibrated = code.calibrated(&calibration, "calibrated").unwrap();
+        calibrated.validate().unwrap();
+        assert_eq!(calibrated.client_info.id, "calibrated");
+        let channels = calibration.pauli_channels();
+        // the qubits with a gate are noisier
+        assert!(channels[0].pz > channels[2].pz && channels[0].py > channels[2].py);
+        for (data_index, channel) in channels.iter().enumerate() {
+            let probabilities = &calibrated.client_info.error_probabilities[data_index];
+            assert_eq!(probabilities["Z"], channel.pz);
+            assert_eq!(probabilities["X"], channel.px);
+            assert!(channel.px > 0.0 && channel.px < 0.01);
+        }
+        // a code with only X errors takes the Y errors as well, since they flip the same Z stabilizers
+        let bit_flip = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let calibrated = bit_flip.calibrated(&calibration, "calibrated").unwrap();
+        calibrated.validate().unwrap();
+        for (data_index, channel) in channels.iter().enumerate() {
+            let probabilities = &calibrated.client_info.error_probabilities[data_index];
+            assert_eq!(probabilities.len(), 1);
+            assert!((probabilities["X"] - (channel.px + channel.py)).abs() < 1e-12);
+        }
+        // a code with X and Z errors splits the Y errors into both
+        let mut client_info = code.client_info.clone();
+        for actions in client_info.data_qubit_actions.iter_mut() {
+            actions.remove("Y");
+        }
+        let calibrated = ServerCodeInfo::from_client_info(client_info)
+            .calibrated(&calibration, "calibrated")
+            .unwrap();
+        for (data_index, channel) in channels.iter().enumerate() {
+            let probabilities = &calibrated.client_info.error_probabilities[data_index];
+            assert!((probabilities["X"] - (channel.px + channel.py)).abs() < 1e-12);
+            assert!((probabilities["Z"] - (channel.pz + channel.py)).abs() < 1e-12);
+        }
+        // invalid calibrations
+        assert!(DeviceCalibration::parse_csv("qubit,0,100,80\n").is_err());
+        assert!(DeviceCalibration::parse_csv("cycle_time,1\nqubit,1,100,80\n").is_err());
+        assert!(DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100\n").is_err());
+        assert!(
+            DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100,80\ngate,0,0,0.9\n").is_err()
+        );
+        // the qubit indices are not trusted for allocating
+        assert!(
+            DeviceCalibration::parse_csv(&format!("cycle_time,1\nqubit,{},100,80\n", usize::MAX))
+                .is_err()
+        );
+        assert!(DeviceCalibration::parse_csv("cycle_time,1\nqubit,99999999999,100,80\n").is_err());
+        // the readout errors are accepted and validated, but do not change the data qubit channels
+        let readout = DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100,80,0.01\n").unwrap();
+        assert_eq!(readout.qubits[0].readout_error, Some(0.01));
+        let readout = DeviceCalibration::parse_json(
+            r#"{"cycle_time": 1, "qubits": [{"t1": 100, "t2": 80, "readout_error": 0.01}]}"#,
+        )
+        .unwrap();
+        assert_eq!(readout.qubits[0].readout_error, Some(0.01));
+        assert_eq!(
+            readout.pauli_channels(),
+            DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100,80\n")
+                .unwrap()
+                .pauli_channels()
+        );
+        assert!(DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100,80,0.6\n").is_err());
+        assert!(
+            DeviceCalibration::parse_json(
+                r#"{"cycle_time": 1, "qubits": [{"t1": 100, "t2": 80, "readout_error": -0.1}]}"#
+            )
+            .is_err()
+        );
+        let calibration = DeviceCalibration::parse_csv("cycle_time,1\nqubit,0,100,80\n").unwrap();
+        assert!(code.calibrated(&calibration, "calibrated").is_err());
+    }
+}
@@ -8,6 +8,15 @@ pub struct ServerCodeInfo {
     pub client_info: ClientCodeInfo,
     pub solver_initializer: SolverInitializer,
     pub edge_errors: Vec<(usize, String)>, // (data_qubit_index, check_type)
+    /// all the errors merged into each hyperedge, including the representative in `edge_errors`
+    #[serde(default)]
+    pub edge_equivalent_errors: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+    /// the correlated errors merged into each hyperedge, as indices of `client_info.correlated_errors`
+    #[serde(default)]
+    pub edge_correlated_errors: Vec<Vec<usize>>,
+    /// the Pauli errors of the most probable error of each hyperedge, on several data qubits if it is correlated
+    #[serde(default)]
+    pub edge_events: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
     pub visualize_positions: Vec<VisualizePosition>,
 }
 
@@ -23,49 +32,493 @@ pub struct ClientCodeInfo {
     pub stabilizer_checks: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
     pub stabilizer_colors: Vec<String>,
     pub logical_observables: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+    /// the probability of each error in `data_qubit_actions`, empty if the errors are uniform;
+    /// the errors not listed have weight 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_probabilities: Vec<HashMap<String, f64>>,
+    /// the errors acting on several data qubits at once, e.g. ZZ crosstalk or bursts, each being a hyperedge
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correlated_errors: Vec<CorrelatedError>,
+    /// whether the stabilizers are the detectors of a detector error model over repeated rounds, whose
+    /// measurement errors are among the errors rather than given as soft information of a single round
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_detector_error_model: bool,
+    /// the coordinates of each detector as given in the detector error model, with an empty list for a detector
+    /// without coordinates; empty for the other codes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detector_coordinates: Vec<Vec<f64>>,
+}
+
+/// an error of several single-qubit Pauli errors happening together with some probability
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CorrelatedError {
+    pub paulis: Vec<(usize, String)>, // (data_qubit_index, error_type)
+    pub probability: f64,
+}
+
+/// the decoding hypergraph of a code with the physical errors of each hyperedge
+#[derive(Debug, Clone)]
+pub struct DecodingGraph {
+    pub solver_initializer: SolverInitializer,
+    /// the most probable single-qubit error of each hyperedge, or the first Pauli error of its most probable
+    /// correlated error if it has no single-qubit error
+    pub edge_errors: Vec<(usize, String)>, // (data_qubit_index, check_type)
+    /// all the single-qubit errors with the syndrome of each hyperedge
+    pub edge_equivalent_errors: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+    /// all the correlated errors with the syndrome of each hyperedge
+    pub edge_correlated_errors: Vec<Vec<usize>>,
+    /// the Pauli errors of the most probable error of each hyperedge
+    pub edge_events: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+}
+
+/// a single-qubit error or a correlated error, as a member of a hyperedge
+enum GraphError {
+    SingleQubit(usize, String),
+    Correlated(usize),
 }
 
 impl ClientCodeInfo {
-    pub fn construct_graph(&self) -> (SolverInitializer, Vec<(usize, String)>) {
-        let vertex_num = self.stabilizer_positions.len();
-        // deduplicate hyperedges
-        let mut hyperedges: HashMap<BTreeSet<usize>, (usize, String)> = HashMap::new();
+    /// check that all the per-qubit and per-stabilizer lists have matching lengths
+    /// and all the indices are in range, so that the code can be safely decoded
+    pub fn check_consistency(&self) -> Result<(), String> {
+        let data_qubit_num = self.data_qubit_positions.len();
+        let stabilizer_num = self.stabilizer_positions.len();
+        if self.data_qubit_actions.len() != data_qubit_num {
+            return Err(format!(
+                "{} data qubit actions for {} data qubits",
+                self.data_qubit_actions.len(),
+                data_qubit_num
+            ));
+        }
+        for (name, len) in [
+            ("stabilizer shapes", self.stabilizer_shapes.len()),
+            ("stabilizer checks", self.stabilizer_checks.len()),
+            ("stabilizer colors", self.stabilizer_colors.len()),
+        ] {
+            if len != stabilizer_num {
+                return Err(format!(
+                    "{} {} for {} stabilizers",
+                    len, name, stabilizer_num
+                ));
+            }
+        }
         for (data_index, actions) in self.data_qubit_actions.iter().enumerate() {
             for (error_type, syndrome) in actions.iter() {
-                let hyperedge = BTreeSet::from_iter(syndrome.iter().cloned());
-                hyperedges
-                    .entry(hyperedge)
-                    .or_insert_with(|| (data_index, error_type.clone()));
+                if !["X", "Y", "Z"].contains(&error_type.as_str()) {
+                    return Err(format!(
+                        "data qubit {} has invalid error type {}",
+                        data_index, error_type
+                    ));
+                }
+                if let Some(stabilizer_idx) = syndrome.iter().find(|&&idx| idx >= stabilizer_num) {
+                    return Err(format!(
+                        "{} error on data qubit {} flips stabilizer {} out of range",
+                        error_type, data_index, stabilizer_idx
+                    ));
+                }
+            }
+        }
+        for (name, operators) in [
+            ("stabilizer check", &self.stabilizer_checks),
+            ("logical observable", &self.logical_observables),
+        ] {
+            for (operator_idx, operator) in operators.iter().enumerate() {
+                for (data_index, check_type) in operator.iter() {
+                    if *data_index >= data_qubit_num {
+                        return Err(format!(
+                            "{} {} acts on data qubit {} out of range",
+                            name, operator_idx, data_index
+                        ));
+                    }
+                    if !["X", "Y", "Z"].contains(&check_type.as_str()) {
+                        return Err(format!(
+                            "{} {} has invalid Pauli type {}",
+                            name, operator_idx, check_type
+                        ));
+                    }
+                }
+            }
+        }
+        if !self.error_probabilities.is_empty() && self.error_probabilities.len() != data_qubit_num
+        {
+            return Err(format!(
+                "{} error probabilities for {} data qubits",
+                self.error_probabilities.len(),
+                data_qubit_num
+            ));
+        }
+        if !self.detector_coordinates.is_empty()
+            && self.detector_coordinates.len() != stabilizer_num
+        {
+            return Err(format!(
+                "{} detector coordinates for {} stabilizers",
+                self.detector_coordinates.len(),
+                stabilizer_num
+            ));
+        }
+        for (data_index, probabilities) in self.error_probabilities.iter().enumerate() {
+            for (error_type, probability) in probabilities.iter() {
+                if !self.data_qubit_actions[data_index].contains_key(error_type) {
+                    return Err(format!(
+                        "probability of {} error on data qubit {} which has no such error",
+                        error_type, data_index
+                    ));
+                }
+                if !(0.0..=1.0).contains(probability) {
+                    return Err(format!(
+                        "probability {} of {} error on data qubit {} is not in [0, 1]",
+                        probability, error_type, data_index
+                    ));
+                }
+            }
+        }
+        for (error_idx, error) in self.correlated_errors.iter().enumerate() {
+            let data_indices: BTreeSet<usize> = error.paulis.iter().map(|(idx, _)| *idx).collect();
+            if data_indices.len() < 2 || data_indices.len() != error.paulis.len() {
+                return Err(format!(
+                    "correlated error {} must act on at least 2 distinct data qubits",
+                    error_idx
+                ));
+            }
+            for (data_index, error_type) in error.paulis.iter() {
+                if !self
+                    .data_qubit_actions
+                    .get(*data_index)
+                    .is_some_and(|actions| actions.contains_key(error_type))
+                {
+                    return Err(format!(
+                        "correlated error {} has a {} error on data qubit {} which has no such error",
+                        error_idx, error_type, data_index
+                    ));
+                }
+            }
+            if !(0.0..=1.0).contains(&error.probability) {
+                return Err(format!(
+                    "probability {} of correlated error {} is not in [0, 1]",
+                    error.probability, error_idx
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// the stabilizers flipped by a correlated error, i.e. the symmetric difference of those of its Pauli errors
+    pub fn correlated_syndrome(&self, error: &CorrelatedError) -> BTreeSet<usize> {
+        let mut syndrome = BTreeSet::new();
+        for (data_index, error_type) in error.paulis.iter() {
+            for &stabilizer_idx in self.data_qubit_actions[*data_index][error_type].iter() {
+                if !syndrome.insert(stabilizer_idx) {
+                    syndrome.remove(&stabilizer_idx);
+                }
+            }
+        }
+        syndrome
+    }
+
+    /// the weight ln((1-p)/p) of an error, which is 1 if its probability is not given
+    pub fn error_weight(&self, data_index: usize, error_type: &str) -> f64 {
+        match self
+            .error_probabilities
+            .get(data_index)
+            .and_then(|probabilities| probabilities.get(error_type))
+        {
+            Some(&probability) => ((1.0 - probability) / probability).ln(),
+            None => 1.0,
+        }
+    }
+
+    /// construct the decoding hypergraph with one hyperedge per distinct syndrome; the edge indices are stable
+    /// across runs, ordered by the first (data qubit, error type) with that syndrome, with the error types of
+    /// each data qubit in alphabetical order, followed by the correlated errors in order. The errors with the
+    /// same syndrome are merged into a single hyperedge that flips with probability p = p1 (1 - p2) + p2 (1 - p1),
+    /// i.e. 1 - 2p = (1 - 2p1) (1 - 2p2), and the most probable one is chosen as the representative
+    pub fn construct_graph(&self) -> DecodingGraph {
+        let vertex_num = self.stabilizer_positions.len();
+        // group the errors by their syndromes
+        let mut edge_indices: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        let mut hyperedges: Vec<(BTreeSet<usize>, Vec<(GraphError, f64)>)> = vec![];
+        let mut add_error = |syndrome: BTreeSet<usize>, error: GraphError, weight: f64| {
+            if syndrome.is_empty() {
+                return; // undetectable error, e.g. a qubit outside of any check
+            }
+            if weight.is_infinite() && weight > 0.0 {
+                return; // the error never happens
+            }
+            let edge_index = *edge_indices.entry(syndrome.clone()).or_insert_with(|| {
+                hyperedges.push((syndrome, vec![]));
+                hyperedges.len() - 1
+            });
+            hyperedges[edge_index].1.push((error, weight));
+        };
+        for (data_index, actions) in self.data_qubit_actions.iter().enumerate() {
+            let mut error_types: Vec<&String> = actions.keys().collect();
+            error_types.sort();
+            for error_type in error_types {
+                add_error(
+                    actions[error_type].iter().cloned().collect(),
+                    GraphError::SingleQubit(data_index, error_type.clone()),
+                    self.error_weight(data_index, error_type),
+                );
             }
         }
+        for (error_idx, error) in self.correlated_errors.iter().enumerate() {
+            add_error(
+                self.correlated_syndrome(error),
+                GraphError::Correlated(error_idx),
+                ((1.0 - error.probability) / error.probability).ln(),
+            );
+        }
         // construct weighted edges
+        let mut graph = DecodingGraph {
+            solver_initializer: SolverInitializer::new(vertex_num, vec![]),
+            edge_errors: vec![],
+            edge_equivalent_errors: vec![],
+            edge_correlated_errors: vec![],
+            edge_events: vec![],
+        };
         let mut weighted_edges = vec![];
-        let mut edge_errors = vec![];
-        for (hyperedge, (data_index, error_type)) in hyperedges.iter() {
+        for (hyperedge, errors) in hyperedges {
+            let weight = merged_weight(errors.iter().map(|(_, weight)| *weight));
             weighted_edges.push(HyperEdge::new(
-                hyperedge.iter().cloned().collect(),
-                1.0.into(),
+                hyperedge.into_iter().collect(),
+                weight.into(),
             ));
-            edge_errors.push((*data_index, error_type.clone()));
+            let (representative, _) = errors
+                .iter()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .unwrap();
+            let event = match representative {
+                GraphError::SingleQubit(data_index, error_type) => {
+                    vec![(*data_index, error_type.clone())]
+                }
+                GraphError::Correlated(error_idx) => {
+                    self.correlated_errors[*error_idx].paulis.clone()
+                }
+            };
+            let single_qubit = errors
+                .iter()
+                .filter_map(|(error, weight)| match error {
+                    GraphError::SingleQubit(data_index, error_type) => {
+                        Some(((*data_index, error_type.clone()), *weight))
+                    }
+                    GraphError::Correlated(_) => None,
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            graph.edge_errors.push(match single_qubit {
+                Some((error, _)) => error,
+                None => event[0].clone(),
+            });
+            graph.edge_events.push(event);
+            let (mut equivalent_errors, mut correlated_errors) = (vec![], vec![]);
+            for (error, _) in errors {
+                match error {
+                    GraphError::SingleQubit(data_index, error_type) => {
+                        equivalent_errors.push((data_index, error_type))
+                    }
+                    GraphError::Correlated(error_idx) => correlated_errors.push(error_idx),
+                }
+            }
+            graph.edge_equivalent_errors.push(equivalent_errors);
+            graph.edge_correlated_errors.push(correlated_errors);
         }
-        (
-            SolverInitializer::new(vertex_num, weighted_edges),
-            edge_errors,
-        )
+        graph.solver_initializer = SolverInitializer::new(vertex_num, weighted_edges);
+        graph
     }
 }
 
+impl ServerCodeInfo {
+    /// construct the decoding graph and the visualizer positions of a code
+    pub fn from_client_info(client_info: ClientCodeInfo) -> Self {
+        let graph = client_info.construct_graph();
+        let visualize_positions = client_info
+            .stabilizer_positions
+            .iter()
+            .map(|(i, j)| VisualizePosition::new(*i * VISUALIZE_SCALE, *j * VISUALIZE_SCALE, 0.0))
+            .collect();
+        Self {
+            client_info,
+            edge_errors: graph.edge_errors,
+            edge_equivalent_errors: graph.edge_equivalent_errors,
+            edge_correlated_errors: graph.edge_correlated_errors,
+            edge_events: graph.edge_events,
+            solver_initializer: graph.solver_initializer,
+            visualize_positions,
+        }
+    }
+
+    /// a variant of the code with additional correlated errors, each becoming a hyperedge or merged into one
+    pub fn with_correlated_errors(
+        &self,
+        correlated_errors: &[CorrelatedError],
+        id: &str,
+    ) -> Result<ServerCodeInfo, String> {
+        let mut client_info = self.client_info.clone();
+        client_info.id = id.to_string();
+        client_info.name = format!("{} (Correlated)", self.client_info.name);
+        client_info
+            .correlated_errors
+            .extend(correlated_errors.iter().cloned());
+        client_info.check_consistency()?;
+        Ok(ServerCodeInfo::from_client_info(client_info))
+    }
+
+    /// the erased hyperedges together with the hyperedges of any error on the erased data qubits,
+    /// sorted and without duplicates
+    pub fn erased_edges(
+        &self,
+        data_qubits: &[usize],
+        edges: &[usize],
+    ) -> Result<Vec<usize>, String> {
+        let data_qubit_num = self.client_info.data_qubit_actions.len();
+        if let Some(data_index) = data_qubits.iter().find(|&&idx| idx >= data_qubit_num) {
+            return Err(format!("erased data qubit {} out of range", data_index));
+        }
+        let edge_num = self.solver_initializer.weighted_edges.len();
+        if let Some(edge_index) = edges.iter().find(|&&idx| idx >= edge_num) {
+            return Err(format!("erased hyperedge {} out of range", edge_index));
+        }
+        let data_qubits: BTreeSet<usize> = data_qubits.iter().cloned().collect();
+        let mut erased: BTreeSet<usize> = edges.iter().cloned().collect();
+        for (edge_index, errors) in self.edge_equivalent_errors.iter().enumerate() {
+            if errors
+                .iter()
+                .any(|(data_index, _)| data_qubits.contains(data_index))
+            {
+                erased.insert(edge_index);
+            }
+        }
+        Ok(erased.into_iter().collect())
+    }
+}
+
+/// set the erased hyperedges to zero weight, as they are known to be flipped at random
+pub fn erase_edges(initializer: &mut SolverInitializer, erasures: &[usize]) {
+    for &edge_index in erasures.iter() {
+        initializer.weighted_edges[edge_index].weight = 0.0.into();
+    }
+}
+
+/// the weight of independent errors of the given weights merged into one hyperedge, which flips with probability
+/// p = p1 (1 - p2) + p2 (1 - p1); a single error keeps its weight, and errors with p >= 0.5 are free to take
+pub fn merged_weight(weights: impl Iterator<Item = f64>) -> f64 {
+    let weights: Vec<f64> = weights.collect();
+    if weights.len() == 1 {
+        return weights[0].max(0.0);
+    }
+    // 1 - 2p = prod (1 - 2p_i) with 1 - 2p_i = tanh(w_i / 2), multiplied in the log space through
+    // 2p_i = 2 / (1 + e^|w_i|), so that the small probabilities of large weights are not rounded to 0
+    let mut log_product = 0.0;
+    let mut is_negative = false;
+    for weight in weights.iter() {
+        is_negative ^= *weight < 0.0;
+        let exp_minus = (-weight.abs()).exp();
+        log_product += (-2.0 * exp_minus / (1.0 + exp_minus)).ln_1p();
+    }
+    if is_negative {
+        return 0.0;
+    }
+    // w = ln((1 - p) / p) = ln((2 - 2p) / 2p)
+    let double_p = -log_product.exp_m1();
+    let weight = ((2.0 - double_p) / double_p).ln();
+    // merging more errors never decreases the probability, which also keeps the weight finite
+    let min_weight = weights.iter().cloned().fold(f64::INFINITY, f64::min);
+    weight.min(min_weight).max(0.0)
+}
+
+/// whether two single-qubit Pauli operators anticommute
+pub fn pauli_anticommute(a: &str, b: &str) -> bool {
+    a != "I" && b != "I" && a != b
+}
+
+/// derive the syndrome of every single-qubit Pauli error from the stabilizer checks;
+/// the errors that are not detected by any stabilizer are recorded with an empty syndrome
+pub fn data_qubit_actions_from_checks(
+    data_qubit_num: usize,
+    stabilizer_checks: &[Vec<(usize, String)>],
+    error_types: &[&str],
+) -> Vec<HashMap<String, Vec<usize>>> {
+    let mut data_qubit_actions: Vec<HashMap<String, Vec<usize>>> = (0..data_qubit_num)
+        .map(|_| {
+            error_types
+                .iter()
+                .map(|error_type| (error_type.to_string(), vec![]))
+                .collect()
+        })
+        .collect();
+    for (stabilizer_idx, check) in stabilizer_checks.iter().enumerate() {
+        for (data_index, check_type) in check.iter() {
+            for (error_type, syndrome) in data_qubit_actions[*data_index].iter_mut() {
+                if pauli_anticommute(error_type, check_type) {
+                    syndrome.push(stabilizer_idx);
+                }
+            }
+        }
+    }
+    data_qubit_actions
+}
+
 #[derive(Default, Clone)]
 pub enum NoiseType {
     #[default]
     Depolarize,
     BitFlip,
     OnlyY,
+    /// independent X, Y and Z errors with the given probabilities on every data qubit
+    Pauli(PauliChannel),
+    /// errors of total probability `p` biased towards Z errors, see [`PauliChannel::biased`]
+    Biased {
+        p: f64,
+        eta: f64,
+    },
 }
 
-const RED: &str = "#ffe8e8";
-const GREEN: &str = "#e8f5e8";
-const BLUE: &str = "#e8f0ff";
+/// the probabilities of X, Y and Z errors on a data qubit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PauliChannel {
+    pub px: f64,
+    pub py: f64,
+    pub pz: f64,
+}
+
+impl PauliChannel {
+    pub fn new(px: f64, py: f64, pz: f64) -> Result<Self, String> {
+        for (name, probability) in [("px", px), ("py", py), ("pz", pz)] {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(format!("{} = {} is not in [0, 1]", name, probability));
+            }
+        }
+        if px + py + pz > 1.0 {
+            return Err(format!("px + py + pz = {} exceeds 1", px + py + pz));
+        }
+        Ok(Self { px, py, pz })
+    }
+
+    /// the channel of total error probability `p` with bias eta = pz / (px + py) and px = py;
+    /// eta = 0.5 is the depolarizing channel and eta = infinity is pure dephasing
+    pub fn biased(p: f64, eta: f64) -> Result<Self, String> {
+        if eta.is_nan() || eta < 0.0 {
+            return Err(format!("bias eta = {} is negative", eta));
+        }
+        if eta.is_infinite() {
+            return Self::new(0.0, 0.0, p);
+        }
+        let pz = p * eta / (1.0 + eta);
+        Self::new((p - pz) / 2.0, (p - pz) / 2.0, pz)
+    }
+
+    pub fn probability(&self, error_type: &str) -> f64 {
+        match error_type {
+            "X" => self.px,
+            "Y" => self.py,
+            "Z" => self.pz,
+            _ => 0.0,
+        }
+    }
+}
+
+pub(crate) const RED: &str = "#ffe8e8";
+pub(crate) const GREEN: &str = "#e8f5e8";
+pub(crate) const BLUE: &str = "#e8f0ff";
 
 impl NoiseType {
     pub fn has_error(&self, error_type: &str) -> bool {
@@ -73,6 +526,67 @@ impl NoiseType {
             NoiseType::Depolarize => error_type == "X" || error_type == "Y" || error_type == "Z",
             NoiseType::BitFlip => error_type == "X",
             NoiseType::OnlyY => error_type == "Y",
+            NoiseType::Pauli(_) | NoiseType::Biased { .. } => self
+                .channel()
+                .is_some_and(|channel| channel.probability(error_type) > 0.0),
+        }
+    }
+
+    /// the probabilities of the errors, `None` if every error has the default weight
+    pub fn channel(&self) -> Option<PauliChannel> {
+        match self {
+            NoiseType::Pauli(channel) => Some(*channel),
+            NoiseType::Biased { p, eta } => PauliChannel::biased(*p, *eta).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for NoiseType {
+    type Err = String;
+
+    /// parse the noise type as printed in the code id, e.g. `depolarize`, `pauli-0.01-0.001-0.02` for
+    /// (px, py, pz) or `biased-0.01-100` for (p, eta)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |parameters: &str, expected: usize| -> Result<Vec<f64>, String> {
+            let values = parameters
+                .split('-')
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid noise parameter {}", value))
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            if values.len() != expected {
+                return Err(format!(
+                    "expect {} noise parameters, found {}",
+                    expected,
+                    values.len()
+                ));
+            }
+            Ok(values)
+        };
+        match s {
+            "depolarize" => Ok(NoiseType::Depolarize),
+            "bit-flip" => Ok(NoiseType::BitFlip),
+            "only-Y" => Ok(NoiseType::OnlyY),
+            _ => {
+                if let Some(parameters) = s.strip_prefix("pauli-") {
+                    let values = parse(parameters, 3)?;
+                    Ok(NoiseType::Pauli(PauliChannel::new(
+                        values[0], values[1], values[2],
+                    )?))
+                } else if let Some(parameters) = s.strip_prefix("biased-") {
+                    let values = parse(parameters, 2)?;
+                    PauliChannel::biased(values[0], values[1])?;
+                    Ok(NoiseType::Biased {
+                        p: values[0],
+                        eta: values[1],
+                    })
+                } else {
+                    Err(format!("unknown noise type {}", s))
+                }
+            }
         }
     }
 }
@@ -86,6 +600,9 @@ impl std::fmt::Display for NoiseType {
                 NoiseType::Depolarize => "depolarize".to_string(),
                 NoiseType::BitFlip => "bit-flip".to_string(),
                 NoiseType::OnlyY => "only-Y".to_string(),
+                NoiseType::Pauli(channel) =>
+                    format!("pauli-{}-{}-{}", channel.px, channel.py, channel.pz),
+                NoiseType::Biased { p, eta } => format!("biased-{}-{}", p, eta),
             }
         )
     }
@@ -100,13 +617,18 @@ impl std::fmt::Debug for NoiseType {
                 NoiseType::Depolarize => "Depolarize".to_string(),
                 NoiseType::BitFlip => "Bit-Flip".to_string(),
                 NoiseType::OnlyY => "Only Y".to_string(),
+                NoiseType::Pauli(channel) => format!(
+                    "Pauli px={}, py={}, pz={}",
+                    channel.px, channel.py, channel.pz
+                ),
+                NoiseType::Biased { p, eta } => format!("Biased p={}, eta={}", p, eta),
             }
         )
     }
 }
 
 const RSC_SCALE: f64 = 1.0;
-const VISUALIZE_SCALE: f64 = 0.5;
+pub(crate) const VISUALIZE_SCALE: f64 = 0.5;
 const RSC_ROUND_SEGMENTS: usize = 36; // how many points on the round
 
 #[derive(Debug, Default, Clone)]
@@ -134,6 +656,17 @@ impl RotatedSurfaceCode {
         code
     }
 
+    /// the code of an id like `rsc-pauli-0.01-0.001-0.02-d-5`, i.e. `rsc-{noise_type}-d-{d}`, for generating
+    /// codes with any noise on demand; returns `None` if the id is not of this form or d is not odd in [3, max_d]
+    pub fn from_code_id(code_id: &str, max_d: usize) -> Option<Self> {
+        let (noise_type, d) = code_id.strip_prefix("rsc-")?.rsplit_once("-d-")?;
+        let d: usize = d.parse().ok()?;
+        if !(3..=max_d).contains(&d) || d % 2 == 0 {
+            return None;
+        }
+        Some(Self::new(d, noise_type.parse().ok()?))
+    }
+
     pub fn is_qubit(&self, i: usize, j: usize) -> bool {
         if i > 2 * self.d || j > 2 * self.d {
             return false;
@@ -327,7 +860,7 @@ impl From<&RotatedSurfaceCode> for ServerCodeInfo {
         // X logical observables
         let mut x_observable: Vec<(usize, String)> = vec![];
         for j in 0..(2 * code.d + 1) {
-            if code.is_data_qubit(j, 1) {
+            if code.is_data_qubit(1, j) {
                 x_observable.push((code.position_to_data_qubit[&(1, j)], "X".to_string()));
             }
         }
@@ -335,6 +868,20 @@ impl From<&RotatedSurfaceCode> for ServerCodeInfo {
             NoiseType::Depolarize => "".to_string(),
             NoiseType::BitFlip => "Bit-Flip, ".to_string(),
             NoiseType::OnlyY => "Y-Flip, ".to_string(),
+            NoiseType::Pauli(_) | NoiseType::Biased { .. } => format!("{:?}, ", code.noise_type),
+        };
+        let error_probabilities = match code.noise_type.channel() {
+            Some(channel) => code
+                .data_qubit_actions
+                .iter()
+                .map(|actions| {
+                    actions
+                        .keys()
+                        .map(|error_type| (error_type.clone(), channel.probability(error_type)))
+                        .collect()
+                })
+                .collect(),
+            None => vec![],
         };
         let client_info = ClientCodeInfo {
             id: format!("rsc-{}-d-{}", code.noise_type, code.d),
@@ -351,19 +898,12 @@ impl From<&RotatedSurfaceCode> for ServerCodeInfo {
             stabilizer_colors: code.stabilizer_colors(),
             data_qubit_actions: code.data_qubit_actions.clone(),
             logical_observables: vec![z_observable, x_observable],
+            error_probabilities,
+            correlated_errors: vec![],
+            is_detector_error_model: false,
+            detector_coordinates: vec![],
         };
-        let (solver_initializer, edge_errors) = client_info.construct_graph();
-        let visualize_positions = client_info
-            .stabilizer_positions
-            .iter()
-            .map(|(i, j)| VisualizePosition::new(*i * VISUALIZE_SCALE, *j * VISUALIZE_SCALE, 0.0))
-            .collect();
-        Self {
-            client_info,
-            edge_errors,
-            solver_initializer,
-            visualize_positions,
-        }
+        Self::from_client_info(client_info)
     }
 }
 
@@ -566,13 +1106,19 @@ impl TriangularColorCodeBitFlip {
 
 impl From<&TriangularColorCodeBitFlip> for ServerCodeInfo {
     fn from(code: &TriangularColorCodeBitFlip) -> Self {
-        // Z logical observables
-        let mut z_observable = vec![];
-        for j in code.range_for_row(0) {
-            if code.is_data_qubit(0, j) {
-                z_observable.push((code.position_to_data_qubit[&(0, j)], "Z".to_string()));
-            }
-        }
+        // the color code is self-dual: its X stabilizers have the same supports as the Z stabilizers,
+        // although only the latter are measured to decode bit-flip errors
+        let stabilizer_checks = code.stabilizer_checks();
+        let mut full_checks = stabilizer_checks.clone();
+        full_checks.extend(stabilizer_checks.iter().map(|check| {
+            check
+                .iter()
+                .map(|(data_index, _)| (*data_index, "X".to_string()))
+                .collect()
+        }));
+        let logical_basis =
+            crate::symplectic::logical_basis(code.data_qubit_positions.len(), &full_checks)
+                .expect("the color code stabilizers commute");
         let client_info = ClientCodeInfo {
             id: format!("color-d-{}", code.d),
             name: format!("Color Code (Bit-Flip, d={})", code.d),
@@ -584,23 +1130,16 @@ impl From<&TriangularColorCodeBitFlip> for ServerCodeInfo {
                 .map(|stabilizer_idx| code.stabilizer_f64_position(stabilizer_idx))
                 .collect(),
             stabilizer_shapes: code.stabilizer_shapes(),
-            stabilizer_checks: code.stabilizer_checks(),
+            stabilizer_checks,
             stabilizer_colors: code.stabilizer_colors(),
             data_qubit_actions: code.data_qubit_actions.clone(),
-            logical_observables: vec![z_observable],
+            logical_observables: logical_basis.observables(),
+            error_probabilities: vec![],
+            correlated_errors: vec![],
+            is_detector_error_model: false,
+            detector_coordinates: vec![],
         };
-        let (solver_initializer, edge_errors) = client_info.construct_graph();
-        let visualize_positions = client_info
-            .stabilizer_positions
-            .iter()
-            .map(|(i, j)| VisualizePosition::new(*i * VISUALIZE_SCALE, *j * VISUALIZE_SCALE, 0.0))
-            .collect();
-        Self {
-            client_info,
-            edge_errors,
-            solver_initializer,
-            visualize_positions,
-        }
+        Self::from_client_info(client_info)
     }
 }
 
@@ -633,4 +1172,239 @@ mod tests {
             serde_json::to_string(&ServerCodeInfo::from(&code)).unwrap()
         );
     }
+
+    #[test]
+    fn test_pauli_channel_noise() {
+        // cargo test -- test_pauli_channel_noise --nocapture
+        let channel = PauliChannel::biased(0.03, 0.5).unwrap();
+        for probability in [channel.px, channel.py, channel.pz] {
+            assert!((probability - 0.01).abs() < 1e-12);
+        }
+        assert_eq!(
+            PauliChannel::biased(0.03, f64::INFINITY).unwrap(),
+            PauliChannel::new(0.0, 0.0, 0.03).unwrap()
+        );
+        assert!(PauliChannel::new(0.5, 0.5, 0.5).is_err());
+        let code = RotatedSurfaceCode::from_code_id("rsc-pauli-0.01-0.001-0.02-d-3", 9).unwrap();
+        let code = ServerCodeInfo::from(&code);
+        assert_eq!(code.client_info.id, "rsc-pauli-0.01-0.001-0.02-d-3");
+        let weight = |probability: f64| ((1.0 - probability) / probability).ln();
+        for (data_index, actions) in code.client_info.data_qubit_actions.iter().enumerate() {
+            assert_eq!(actions.len(), 3);
+            for (error_type, probability) in [("X", 0.01), ("Y", 0.001), ("Z", 0.02)] {
+                let error_weight = code.client_info.error_weight(data_index, error_type);
+                assert!((error_weight - weight(probability)).abs() < 1e-9);
+            }
+        }
+        // pure dephasing has no X or Y error
+        let code = RotatedSurfaceCode::from_code_id("rsc-biased-0.01-inf-d-3", 9).unwrap();
+        assert!(!code.noise_type.has_error("X") && code.noise_type.has_error("Z"));
+        assert!(RotatedSurfaceCode::from_code_id("rsc-pauli-0.01-0.01-d-3", 9).is_none());
+        assert!(RotatedSurfaceCode::from_code_id("rsc-depolarize-d-11", 9).is_none());
+    }
+
+    #[test]
+    fn test_erased_edges() {
+        // cargo test -- test_erased_edges --nocapture
+        use num_traits::cast::ToPrimitive;
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        // the X, Y and Z errors of the center qubit have distinct syndromes
+        let erasures = code.erased_edges(&[4], &[]).unwrap();
+        assert_eq!(erasures.len(), 3);
+        for &edge_index in erasures.iter() {
+            assert!(
+                code.edge_equivalent_errors[edge_index]
+                    .iter()
+                    .any(|(data_index, _)| *data_index == 4)
+            );
+        }
+        assert_eq!(code.erased_edges(&[4], &erasures).unwrap(), erasures);
+        let mut initializer = code.solver_initializer.clone();
+        erase_edges(&mut initializer, &erasures);
+        for (edge_index, hyperedge) in initializer.weighted_edges.iter().enumerate() {
+            assert_eq!(
+                hyperedge.weight.to_f64().unwrap() == 0.0,
+                erasures.contains(&edge_index)
+            );
+        }
+        assert!(code.erased_edges(&[9], &[]).is_err());
+        assert!(code.erased_edges(&[], &[code.edge_errors.len()]).is_err());
+    }
+
+    #[test]
+    fn test_correlated_errors() {
+        // cargo test -- test_correlated_errors --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        let zz = |a: usize, b: usize, probability: f64| CorrelatedError {
+            paulis: vec![(a, "Z".to_string()), (b, "Z".to_string())],
+            probability,
+        };
+        // ZZ on the neighboring qubits 3 and 4, and a burst on three qubits
+        let burst = CorrelatedError {
+            paulis: vec![
+                (0, "Y".to_string()),
+                (1, "Y".to_string()),
+                (2, "Y".to_string()),
+            ],
+            probability: 0.01,
+        };
+        let correlated = code
+            .with_correlated_errors(&[zz(3, 4, 0.05), burst.clone()], "correlated")
+            .unwrap();
+        correlated.validate().unwrap();
+        let edge_num = code.solver_initializer.weighted_edges.len();
+        // the existing hyperedges keep their indices
+        assert_eq!(correlated.edge_errors[..edge_num], code.edge_errors[..]);
+        let syndrome = correlated
+            .client_info
+            .correlated_syndrome(&correlated.client_info.correlated_errors[0]);
+        let edge_index = correlated
+            .solver_initializer
+            .weighted_edges
+            .iter()
+            .position(|hyperedge| {
+                hyperedge
+                    .vertices
+                    .iter()
+                    .cloned()
+                    .collect::<BTreeSet<usize>>()
+                    == syndrome
+            })
+            .unwrap();
+        assert!(correlated.edge_correlated_errors[edge_index].contains(&0));
+        let burst_edge = correlated
+            .edge_correlated_errors
+            .iter()
+            .position(|errors| errors.contains(&1))
+            .unwrap();
+        if correlated.edge_equivalent_errors[burst_edge].is_empty() {
+            assert_eq!(correlated.edge_events[burst_edge], burst.paulis);
+            assert_eq!(correlated.edge_errors[burst_edge], burst.paulis[0]);
+        }
+        // invalid correlated errors
+        for error in [
+            zz(3, 3, 0.1),
+            zz(3, 9, 0.1),
+            zz(3, 4, 1.5),
+            CorrelatedError {
+                paulis: vec![(3, "Z".to_string())],
+                probability: 0.1,
+            },
+        ] {
+            assert!(code.with_correlated_errors(&[error], "invalid").is_err());
+        }
+    }
+
+    #[test]
+    fn test_merged_weight() {
+        // cargo test -- test_merged_weight --nocapture
+        let tanh_merged = |a: f64, b: f64| 2.0 * ((a / 2.0).tanh() * (b / 2.0).tanh()).atanh();
+        assert!((merged_weight([1.0, 2.0].into_iter()) - tanh_merged(1.0, 2.0)).abs() < 1e-9);
+        assert_eq!(merged_weight([3.0].into_iter()), 3.0);
+        assert_eq!(merged_weight([0.0, 5.0].into_iter()), 0.0);
+        assert_eq!(merged_weight([-1.0, 5.0].into_iter()), 0.0);
+        // two errors of the same small probability p merge into about 2p
+        for weight in [40.0, 100.0, 700.0] {
+            let merged = merged_weight([weight, weight].into_iter());
+            assert!((merged - (weight - 2f64.ln())).abs() < 1e-6, "{}", merged);
+        }
+        // the probabilities too small for f64 still give a finite weight
+        let merged = merged_weight([1000.0, 2000.0].into_iter());
+        assert!(merged.is_finite() && merged <= 1000.0);
+    }
+
+    #[test]
+    fn test_construct_graph_deterministic() {
+        // cargo test -- test_construct_graph_deterministic --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(5, NoiseType::Depolarize));
+        let graph = code.client_info.construct_graph();
+        // rebuild the `HashMap`s with rotated insertion orders; each new map also gets its own random seed,
+        // so the iteration orders differ from the original ones, which is checked so that the test is not vacuous
+        let iteration_order = |client_info: &ClientCodeInfo| -> Vec<Vec<String>> {
+            client_info
+                .data_qubit_actions
+                .iter()
+                .map(|actions| actions.keys().cloned().collect())
+                .collect()
+        };
+        let mut is_order_different = false;
+        for rotation in 0..8 {
+            let mut client_info = code.client_info.clone();
+            client_info.data_qubit_actions = client_info
+                .data_qubit_actions
+                .iter()
+                .map(|actions| {
+                    let mut actions: Vec<_> = actions.clone().into_iter().collect();
+                    actions.sort();
+                    let len = actions.len();
+                    actions.rotate_left(rotation % len.max(1));
+                    let mut rebuilt = HashMap::with_capacity(rotation + len);
+                    rebuilt.extend(actions);
+                    rebuilt
+                })
+                .collect();
+            is_order_different |=
+                iteration_order(&client_info) != iteration_order(&code.client_info);
+            let graph_2 = client_info.construct_graph();
+            assert_eq!(graph.edge_errors, graph_2.edge_errors);
+            for (edge, edge_2) in graph
+                .solver_initializer
+                .weighted_edges
+                .iter()
+                .zip(graph_2.solver_initializer.weighted_edges.iter())
+            {
+                assert_eq!(edge.vertices, edge_2.vertices);
+            }
+        }
+        assert!(is_order_different);
+        let edge_errors = graph.edge_errors;
+        assert_eq!(edge_errors[0], (0, "X".to_string()));
+        assert!(edge_errors.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_construct_graph_merge_errors() {
+        // cargo test -- test_construct_graph_merge_errors --nocapture
+        use crate::parity_check::*;
+        use num_traits::cast::ToPrimitive;
+        // a single check of 3 bits, so that the bit flips on all of them have the same syndrome
+        let mut client_info = ParityCheckCode {
+            h: Some(BinaryMatrix::Sparse {
+                columns: 3,
+                rows: vec![vec![0, 1, 2]],
+            }),
+            ..Default::default()
+        }
+        .build_client_info("single-check")
+        .unwrap();
+        let graph = client_info.construct_graph();
+        let edge_index = 0;
+        let equivalent_errors = &graph.edge_equivalent_errors[edge_index];
+        assert_eq!(equivalent_errors.len(), 3);
+        assert_eq!(graph.edge_errors[edge_index], equivalent_errors[0]);
+        // uniform errors of weight 1 have p = 1 / (1 + e), and 1 - 2p is multiplied when merged
+        let p: f64 = 1.0 / (1.0 + 1f64.exp());
+        let merged = (1.0 - (1.0 - 2.0 * p).powi(3)) / 2.0;
+        let weight = graph.solver_initializer.weighted_edges[edge_index]
+            .weight
+            .to_f64()
+            .unwrap();
+        assert!((weight - ((1.0 - merged) / merged).ln()).abs() < 1e-9);
+        // the more probable error becomes the representative
+        client_info.error_probabilities =
+            vec![HashMap::new(); client_info.data_qubit_actions.len()];
+        let (data_index, error_type) = &equivalent_errors[1];
+        client_info.error_probabilities[*data_index].insert(error_type.clone(), 0.4);
+        client_info.check_consistency().unwrap();
+        let graph_2 = client_info.construct_graph();
+        assert_eq!(graph_2.edge_errors[edge_index], equivalent_errors[1]);
+        assert_eq!(
+            graph_2.edge_equivalent_errors[edge_index],
+            *equivalent_errors
+        );
+        // an impossible error is left out
+        client_info.error_probabilities[*data_index].insert(error_type.clone(), 0.0);
+        let graph_3 = client_info.construct_graph();
+        assert_eq!(graph_3.edge_equivalent_errors[edge_index].len(), 2);
+    }
 }
@@ -0,0 +1,571 @@
+use crate::codes::*;
+use mwpf::visualize::VisualizePosition;
+use std::collections::{BTreeSet, HashMap};
+
+const DEM_SCALE: f64 = 1.0;
+const DEM_SHAPE_RADIUS: f64 = 0.3;
+/// the maximum number of instructions after unrolling the `repeat` blocks
+const DEM_MAX_UNROLLED: usize = 100_000;
+/// the maximum number of detectors or observables, which bounds the allocations per detector
+const DEM_MAX_DETECTORS: usize = 100_000;
+/// the maximum nesting depth of `repeat` blocks, which bounds the recursion when parsing and executing
+const DEM_MAX_DEPTH: usize = 32;
+
+/// a single error mechanism `error(p) D0 D1 L0` in a detector error model
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemError {
+    pub probability: f64,
+    pub detectors: Vec<usize>,
+    pub observables: Vec<usize>,
+}
+
+/// a flattened Stim detector error model, with all the `repeat` blocks unrolled and `shift_detectors` applied
+#[derive(Debug, Clone, Default)]
+pub struct DetectorErrorModel {
+    pub detector_num: usize,
+    pub observable_num: usize,
+    pub errors: Vec<DemError>,
+    /// the coordinates of each detector, if declared by a `detector` instruction
+    pub detector_coordinates: Vec<Option<Vec<f64>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DemTarget {
+    Detector(usize),
+    Observable(usize),
+    /// the `^` separating the components of a suggested decomposition
+    Separator,
+}
+
+#[derive(Debug, Clone)]
+enum DemInstruction {
+    Error {
+        probability: f64,
+        targets: Vec<DemTarget>,
+    },
+    Detector {
+        coordinates: Vec<f64>,
+        targets: Vec<DemTarget>,
+    },
+    LogicalObservable {
+        targets: Vec<DemTarget>,
+    },
+    ShiftDetectors {
+        coordinates: Vec<f64>,
+        offset: usize,
+    },
+    Repeat {
+        count: usize,
+        body: Vec<DemInstruction>,
+    },
+}
+
+#[derive(Debug, Default)]
+struct DemState {
+    detector_offset: usize,
+    coordinate_offset: Vec<f64>,
+}
+
+fn parse_targets(targets: &[String], line_number: usize) -> Result<Vec<DemTarget>, String> {
+    targets
+        .iter()
+        .map(|target| {
+            if target == "^" {
+                return Some(DemTarget::Separator);
+            }
+            let index = target.get(1..)?.parse::<usize>().ok()?;
+            match target.chars().next() {
+                Some('D') => Some(DemTarget::Detector(index)),
+                Some('L') => Some(DemTarget::Observable(index)),
+                _ => None,
+            }
+        })
+        .zip(targets.iter())
+        .map(|(parsed, target)| {
+            parsed.ok_or(format!(
+                "line {}: invalid target \"{}\"",
+                line_number, target
+            ))
+        })
+        .collect()
+}
+
+fn parse_block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    depth: usize,
+) -> Result<Vec<DemInstruction>, String> {
+    let is_nested = depth > 0;
+    let mut instructions = vec![];
+    while let Some((line_number, line)) = lines.next() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "}" {
+            if is_nested {
+                return Ok(instructions);
+            }
+            return Err(format!("line {}: unmatched '}}'", line_number));
+        }
+        // split `name[tag](arguments) targets`
+        let name_end = line
+            .find(|c: char| c == '(' || c == '[' || c.is_whitespace())
+            .unwrap_or(line.len());
+        let name = line[..name_end].to_ascii_lowercase();
+        let mut rest = &line[name_end..];
+        if rest.starts_with('[') {
+            let tag_end = rest
+                .find(']')
+                .ok_or(format!("line {}: unclosed tag", line_number))?;
+            rest = &rest[tag_end + 1..];
+        }
+        let mut arguments = vec![];
+        if rest.starts_with('(') {
+            let arguments_end = rest
+                .find(')')
+                .ok_or(format!("line {}: unclosed arguments", line_number))?;
+            for argument in rest[1..arguments_end]
+                .split(',')
+                .map(str::trim)
+                .filter(|argument| !argument.is_empty())
+            {
+                arguments.push(argument.parse::<f64>().map_err(|_| {
+                    format!("line {}: invalid argument \"{}\"", line_number, argument)
+                })?);
+            }
+            rest = &rest[arguments_end + 1..];
+        }
+        let targets: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+        instructions.push(match name.as_str() {
+            "error" => {
+                if arguments.len() != 1 || !(0.0..=1.0).contains(&arguments[0]) {
+                    return Err(format!(
+                        "line {}: error expects a single probability",
+                        line_number
+                    ));
+                }
+                DemInstruction::Error {
+                    probability: arguments[0],
+                    targets: parse_targets(&targets, line_number)?,
+                }
+            }
+            "detector" => DemInstruction::Detector {
+                coordinates: arguments,
+                targets: parse_targets(&targets, line_number)?,
+            },
+            "logical_observable" => DemInstruction::LogicalObservable {
+                targets: parse_targets(&targets, line_number)?,
+            },
+            "shift_detectors" => DemInstruction::ShiftDetectors {
+                coordinates: arguments,
+                offset: match targets.as_slice() {
+                    [] => 0,
+                    [offset] => offset.parse().map_err(|_| {
+                        format!(
+                            "line {}: invalid detector shift \"{}\"",
+                            line_number, offset
+                        )
+                    })?,
+                    _ => {
+                        return Err(format!(
+                            "line {}: shift_detectors expects a single offset",
+                            line_number
+                        ));
+                    }
+                },
+            },
+            "repeat" => {
+                let count = match targets.as_slice() {
+                    [count, brace] if brace == "{" => count.parse::<usize>().ok(),
+                    _ => None,
+                }
+                .ok_or(format!(
+                    "line {}: repeat expects `repeat <count> {{`",
+                    line_number
+                ))?;
+                if depth >= DEM_MAX_DEPTH {
+                    return Err(format!(
+                        "line {}: repeat blocks are nested deeper than {}",
+                        line_number, DEM_MAX_DEPTH
+                    ));
+                }
+                DemInstruction::Repeat {
+                    count,
+                    body: parse_block(lines, depth + 1)?,
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "line {}: unknown instruction \"{}\"",
+                    line_number, name
+                ));
+            }
+        });
+    }
+    if is_nested {
+        return Err("unclosed repeat block".to_string());
+    }
+    Ok(instructions)
+}
+
+fn unrolled_size(instructions: &[DemInstruction]) -> usize {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            // an empty body still costs one step per iteration
+            DemInstruction::Repeat { count, body } => {
+                count.saturating_mul(unrolled_size(body).max(1))
+            }
+            _ => 1,
+        })
+        .fold(0, usize::saturating_add)
+}
+
+impl DetectorErrorModel {
+    /// parse the text of a Stim `.dem` file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(line_idx, line)| (line_idx + 1, line));
+        let instructions = parse_block(&mut lines, 0)?;
+        if unrolled_size(&instructions) > DEM_MAX_UNROLLED {
+            return Err(format!(
+                "the model has more than {} instructions after unrolling",
+                DEM_MAX_UNROLLED
+            ));
+        }
+        let mut model = Self::default();
+        let mut state = DemState::default();
+        model.execute(&instructions, &mut state)?;
+        model.detector_coordinates.resize(model.detector_num, None);
+        Ok(model)
+    }
+
+    fn execute(
+        &mut self,
+        instructions: &[DemInstruction],
+        state: &mut DemState,
+    ) -> Result<(), String> {
+        let detector_of = |state: &DemState, index: usize| {
+            state
+                .detector_offset
+                .checked_add(index)
+                .filter(|detector| *detector < DEM_MAX_DETECTORS)
+                .ok_or_else(|| {
+                    format!(
+                        "detector D{} after shifting by {} exceeds the limit of {} detectors",
+                        index, state.detector_offset, DEM_MAX_DETECTORS
+                    )
+                })
+        };
+        let check_observable = |observable: usize| {
+            if observable >= DEM_MAX_DETECTORS {
+                return Err(format!(
+                    "observable L{} exceeds the limit of {} observables",
+                    observable, DEM_MAX_DETECTORS
+                ));
+            }
+            Ok(())
+        };
+        for instruction in instructions.iter() {
+            match instruction {
+                DemInstruction::Error {
+                    probability,
+                    targets,
+                } => {
+                    // the detectors and observables flipped an even number of times cancel out
+                    let mut detectors = BTreeSet::new();
+                    let mut observables = BTreeSet::new();
+                    for target in targets.iter() {
+                        match *target {
+                            DemTarget::Detector(index) => {
+                                let detector = detector_of(state, index)?;
+                                self.detector_num = self.detector_num.max(detector + 1);
+                                if !detectors.remove(&detector) {
+                                    detectors.insert(detector);
+                                }
+                            }
+                            DemTarget::Observable(observable) => {
+                                check_observable(observable)?;
+                                self.observable_num = self.observable_num.max(observable + 1);
+                                if !observables.remove(&observable) {
+                                    observables.insert(observable);
+                                }
+                            }
+                            DemTarget::Separator => {}
+                        }
+                    }
+                    self.errors.push(DemError {
+                        probability: *probability,
+                        detectors: detectors.into_iter().collect(),
+                        observables: observables.into_iter().collect(),
+                    });
+                }
+                DemInstruction::Detector {
+                    coordinates,
+                    targets,
+                } => {
+                    for target in targets.iter() {
+                        let DemTarget::Detector(index) = *target else {
+                            continue;
+                        };
+                        let detector = detector_of(state, index)?;
+                        self.detector_num = self.detector_num.max(detector + 1);
+                        let shifted: Vec<f64> = coordinates
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, value)| {
+                                value + state.coordinate_offset.get(idx).cloned().unwrap_or(0.0)
+                            })
+                            .collect();
+                        if self.detector_coordinates.len() <= detector {
+                            self.detector_coordinates.resize(detector + 1, None);
+                        }
+                        // a detector declared without coordinates has none
+                        self.detector_coordinates[detector] =
+                            Some(shifted).filter(|shifted| !shifted.is_empty());
+                    }
+                }
+                DemInstruction::LogicalObservable { targets } => {
+                    for target in targets.iter() {
+                        if let DemTarget::Observable(observable) = *target {
+                            check_observable(observable)?;
+                            self.observable_num = self.observable_num.max(observable + 1);
+                        }
+                    }
+                }
+                DemInstruction::ShiftDetectors {
+                    coordinates,
+                    offset,
+                } => {
+                    state.detector_offset = state
+                        .detector_offset
+                        .checked_add(*offset)
+                        .filter(|detector_offset| *detector_offset <= DEM_MAX_DETECTORS)
+                        .ok_or_else(|| {
+                            format!(
+                                "shifting the detectors by {} exceeds the limit of {} detectors",
+                                offset, DEM_MAX_DETECTORS
+                            )
+                        })?;
+                    if state.coordinate_offset.len() < coordinates.len() {
+                        state.coordinate_offset.resize(coordinates.len(), 0.0);
+                    }
+                    for (idx, value) in coordinates.iter().enumerate() {
+                        state.coordinate_offset[idx] += value;
+                    }
+                }
+                DemInstruction::Repeat { count, body } => {
+                    for _ in 0..*count {
+                        self.execute(body, state)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the (i, j, t) position of a detector; detectors without coordinates are placed on a line
+    fn detector_position(&self, detector: usize) -> (f64, f64, f64) {
+        match &self.detector_coordinates[detector] {
+            Some(coordinates) => (
+                coordinates.first().cloned().unwrap_or(0.0),
+                coordinates.get(1).cloned().unwrap_or(0.0),
+                coordinates.get(2).cloned().unwrap_or(0.0),
+            ),
+            None => (0.0, detector as f64, 0.0),
+        }
+    }
+
+    /// build a code where each error mechanism is a data qubit whose `X` error flips its detectors,
+    /// and each detector is a `Z` stabilizer; the hyperedge weights are ln((1-p)/p)
+    pub fn build(&self, id: &str, name: &str) -> ServerCodeInfo {
+        // in the 2D view, the layers of different time coordinates are placed side by side
+        let positions: Vec<(f64, f64, f64)> = (0..self.detector_num)
+            .map(|detector| self.detector_position(detector))
+            .collect();
+        let (i_min, i_max) = positions
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), (i, _, _)| {
+                (min.min(*i), max.max(*i))
+            });
+        let layer_interval = if positions.is_empty() {
+            0.0
+        } else {
+            i_max - i_min + 2.0 * DEM_SCALE
+        };
+        let stabilizer_positions: Vec<(f64, f64)> = positions
+            .iter()
+            .map(|(i, j, t)| (i + t * layer_interval, *j))
+            .collect();
+        let stabilizer_shapes: Vec<Vec<(f64, f64)>> = stabilizer_positions
+            .iter()
+            .map(|(i, j)| {
+                [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (1.0, -1.0)]
+                    .iter()
+                    .map(|(di, dj)| (i + di * DEM_SHAPE_RADIUS, j + dj * DEM_SHAPE_RADIUS))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let mut stabilizer_checks: Vec<Vec<(usize, String)>> = vec![vec![]; self.detector_num];
+        let mut logical_observables: Vec<Vec<(usize, String)>> = vec![vec![]; self.observable_num];
+        let mut data_qubit_positions = vec![];
+        let mut data_qubit_actions = vec![];
+        for (error_index, error) in self.errors.iter().enumerate() {
+            for &detector in error.detectors.iter() {
+                stabilizer_checks[detector].push((error_index, "Z".to_string()));
+            }
+            for &observable in error.observables.iter() {
+                logical_observables[observable].push((error_index, "Z".to_string()));
+            }
+            data_qubit_positions.push(if error.detectors.is_empty() {
+                (0.0, 0.0)
+            } else {
+                let count = error.detectors.len() as f64;
+                (
+                    error
+                        .detectors
+                        .iter()
+                        .map(|&detector| stabilizer_positions[detector].0)
+                        .sum::<f64>()
+                        / count,
+                    error
+                        .detectors
+                        .iter()
+                        .map(|&detector| stabilizer_positions[detector].1)
+                        .sum::<f64>()
+                        / count,
+                )
+            });
+            data_qubit_actions.push(HashMap::from([("X".to_string(), error.detectors.clone())]));
+        }
+        let mut client_info = ClientCodeInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            d: 0,
+            data_qubit_positions,
+            data_qubit_actions,
+            stabilizer_positions,
+            stabilizer_shapes,
+            stabilizer_checks,
+            stabilizer_colors: vec![GREEN.to_string(); self.detector_num],
+            logical_observables,
+            error_probabilities: self
+                .errors
+                .iter()
+                .map(|error| HashMap::from([("X".to_string(), error.probability)]))
+                .collect(),
+            correlated_errors: vec![],
+            is_detector_error_model: true,
+            detector_coordinates: self
+                .detector_coordinates
+                .iter()
+                .map(|coordinates| coordinates.clone().unwrap_or_default())
+                .collect(),
+        };
+        // without any detector coordinates, place the detectors and errors by their connectivity
+        let has_coordinates = self.detector_coordinates.iter().any(Option::is_some);
+        if !has_coordinates {
+            client_info.apply_auto_layout();
+        }
+        // the error mechanisms with the same detectors are merged
+        let graph = client_info.construct_graph();
+        let visualize_positions = if has_coordinates {
+            positions
+                .iter()
+                .map(|(i, j, t)| {
+                    VisualizePosition::new(i * VISUALIZE_SCALE, j * VISUALIZE_SCALE, *t)
+                })
+                .collect()
+        } else {
+            client_info
+                .stabilizer_positions
+                .iter()
+                .map(|(i, j)| VisualizePosition::new(i * VISUALIZE_SCALE, j * VISUALIZE_SCALE, 0.0))
+                .collect()
+        };
+        ServerCodeInfo {
+            client_info,
+            solver_initializer: graph.solver_initializer,
+            edge_errors: graph.edge_errors,
+            edge_equivalent_errors: graph.edge_equivalent_errors,
+            edge_correlated_errors: graph.edge_correlated_errors,
+            edge_events: graph.edge_events,
+            visualize_positions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dem() {
+        // cargo test -- test_parse_dem --nocapture
+        let text = "
+            # a repetition code with 2 rounds
+            error(0.1) D0 L0
+            error(0.1) D0 D1
+            error(0.1) D1
+            detector(1, 0, 0) D0
+            detector(3, 0, 0) D1
+            repeat 2 {
+                error(0.01) D0 D2
+                error(0.01) D1 D3
+                shift_detectors(0, 0, 1) 2
+                detector(1, 0, 0) D0
+                detector(3, 0, 0) D1
+            }
+            logical_observable L0
+        ";
+        let model = DetectorErrorModel::parse(text).unwrap();
+        assert_eq!(model.detector_num, 6);
+        assert_eq!(model.observable_num, 1);
+        assert_eq!(model.errors.len(), 7);
+        assert_eq!(model.errors[5].detectors, vec![2, 4]);
+        assert_eq!(model.detector_coordinates[5], Some(vec![3.0, 0.0, 2.0]));
+        let code = model.build("dem-test", "DEM Test");
+        assert_eq!(code.solver_initializer.vertex_num, 6);
+        assert_eq!(code.solver_initializer.weighted_edges.len(), 7);
+        assert_eq!(
+            code.client_info.logical_observables[0],
+            vec![(0, "Z".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_dem() {
+        // cargo test -- test_parse_invalid_dem --nocapture
+        assert!(DetectorErrorModel::parse("error(0.1) D0 X1").is_err());
+        assert!(DetectorErrorModel::parse("error(0.1) D").is_err());
+        assert!(DetectorErrorModel::parse("repeat 2 {\nerror(0.1) D0").is_err());
+        assert!(DetectorErrorModel::parse("}").is_err());
+        assert!(DetectorErrorModel::parse("error(2) D0").is_err());
+        // detector indices that would allocate beyond the limits
+        assert!(DetectorErrorModel::parse("error(0.1) D99999999999").is_err());
+        assert!(DetectorErrorModel::parse("error(0.1) L99999999999").is_err());
+        assert!(
+            DetectorErrorModel::parse(&format!("shift_detectors {}\nerror(0.1) D1", usize::MAX))
+                .is_err()
+        );
+        assert!(
+            DetectorErrorModel::parse("repeat 1000000 {\nshift_detectors 1000\n}\nerror(0.1) D0")
+                .is_err()
+        );
+        assert!(
+            DetectorErrorModel::parse("repeat 100000 {\nrepeat 100000 {\nerror(0.1) D0\n}\n}")
+                .is_err()
+        );
+        // empty blocks still count one instruction per iteration
+        assert!(
+            DetectorErrorModel::parse("repeat 4294967295 {\nrepeat 4294967295 {\n}\n}").is_err()
+        );
+        // deeply nested blocks are rejected instead of overflowing the stack
+        let nested = "repeat 1 {\n".repeat(100_000) + &"}\n".repeat(100_000);
+        assert!(DetectorErrorModel::parse(&nested).is_err());
+        let nested = "repeat 1 {\n".repeat(DEM_MAX_DEPTH) + &"}\n".repeat(DEM_MAX_DEPTH);
+        assert!(DetectorErrorModel::parse(&nested).is_ok());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::codes::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// the default limit of the number of distinct error sets explored by [`ClientCodeInfo::code_distance`]
+pub const DEFAULT_MAX_STATES: usize = 1_000_000;
+/// the memory budget of the search, which lowers the limit of the states for codes with many stabilizers
+const MAX_SEARCH_BYTES: usize = 256 * 1024 * 1024;
+
+/// the distance of a code under its noise model, i.e. the minimum number of error mechanisms
+/// (single-qubit errors in `data_qubit_actions`) that together trigger no stabilizer but flip a logical observable
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeDistance {
+    pub lower_bound: usize,
+    /// `None` if no logical error is found within the search limit
+    pub upper_bound: Option<usize>,
+    pub is_exact: bool,
+    /// a logical error of `upper_bound` error mechanisms
+    pub logical_error: Vec<(usize, String)>, // (data_qubit_index, error_type)
+    /// the logical observables flipped by `logical_error`
+    pub flipped_observables: Vec<usize>,
+    /// the number of distinct error sets explored
+    pub explored_states: usize,
+}
+
+/// an error of a single type on a single data qubit, with the stabilizers and the observables it flips
+struct ErrorMechanism {
+    data_index: usize,
+    error_type: String,
+    syndrome: Vec<u64>,
+    observables: Vec<u64>,
+}
+
+/// a set of error mechanisms reached by adding `mechanism` to the set of `parent`
+struct SearchNode {
+    weight: usize,
+    parent: Option<(usize, usize)>, // (parent node, mechanism)
+    syndrome: Vec<u64>,
+    observables: Vec<u64>,
+}
+
+fn bitset(len: usize, indices: impl Iterator<Item = usize>) -> Vec<u64> {
+    let mut bits = vec![0; len.div_ceil(64)];
+    for index in indices {
+        bits[index / 64] ^= 1u64 << (index % 64);
+    }
+    bits
+}
+
+fn xor(a: &[u64], b: &[u64]) -> Vec<u64> {
+    a.iter().zip(b.iter()).map(|(a, b)| a ^ b).collect()
+}
+
+fn bitset_indices(bits: &[u64]) -> Vec<usize> {
+    (0..bits.len() * 64)
+        .filter(|index| (bits[index / 64] >> (index % 64)) & 1 == 1)
+        .collect()
+}
+
+impl ClientCodeInfo {
+    /// all the error mechanisms in a deterministic order
+    fn error_mechanisms(&self) -> Vec<ErrorMechanism> {
+        let stabilizer_num = self.stabilizer_positions.len();
+        let observable_num = self.logical_observables.len();
+        let mut mechanisms = vec![];
+        for (data_index, actions) in self.data_qubit_actions.iter().enumerate() {
+            let mut error_types: Vec<&String> = actions.keys().collect();
+            error_types.sort();
+            for error_type in error_types {
+                let flipped = self.logical_observables.iter().enumerate().flat_map(
+                    |(observable_idx, observable)| {
+                        observable
+                            .iter()
+                            .filter(move |(index, check_type)| {
+                                *index == data_index && pauli_anticommute(error_type, check_type)
+                            })
+                            .map(move |_| observable_idx)
+                    },
+                );
+                mechanisms.push(ErrorMechanism {
+                    data_index,
+                    error_type: error_type.clone(),
+                    syndrome: bitset(stabilizer_num, actions[error_type].iter().cloned()),
+                    observables: bitset(observable_num, flipped),
+                });
+            }
+        }
+        mechanisms
+    }
+
+    /// compute the code distance by a breadth-first search over the sets of error mechanisms, keeping only the
+    /// smallest set for each (syndrome, flipped observables) signature; two sets of the same syndrome but different
+    /// observables together form a logical error. After exploring all the sets of up to R mechanisms, every logical
+    /// error of up to 2R mechanisms has been found, so the distance is exact once a logical error of at most 2R + 1
+    /// mechanisms is known, and otherwise bounded from below by 2R + 1. The logical observables themselves, when
+    /// made of available error mechanisms, give upper bounds before the search.
+    pub fn code_distance(&self, max_states: usize) -> Result<CodeDistance, String> {
+        self.check_consistency()?;
+        if self.logical_observables.is_empty() {
+            return Err("the code has no logical observable to define the distance".to_string());
+        }
+        let mechanisms = self.error_mechanisms();
+        // each state keeps its syndrome twice, as a node and as a bucket key, besides its observables
+        let state_bytes = (2 * self.stabilizer_positions.len().div_ceil(64)
+            + self.logical_observables.len().div_ceil(64))
+            * 8
+            + 128;
+        let max_states = max_states.min(MAX_SEARCH_BYTES / state_bytes).max(1);
+        let mechanism_index: HashMap<(usize, &str), usize> = mechanisms
+            .iter()
+            .enumerate()
+            .map(|(idx, mechanism)| ((mechanism.data_index, mechanism.error_type.as_str()), idx))
+            .collect();
+        let mut best: Option<BTreeSet<usize>> = None;
+        let consider = |best: &mut Option<BTreeSet<usize>>, logical_error: BTreeSet<usize>| {
+            if best
+                .as_ref()
+                .is_none_or(|best| logical_error.len() < best.len())
+            {
+                *best = Some(logical_error);
+            }
+        };
+        // the logical observables that are themselves made of error mechanisms
+        for observable in self.logical_observables.iter() {
+            let mut logical_error = BTreeSet::new();
+            for (data_index, pauli) in observable.iter() {
+                let Some(&idx) = mechanism_index.get(&(*data_index, pauli.as_str())) else {
+                    logical_error.clear();
+                    break;
+                };
+                if !logical_error.insert(idx) {
+                    logical_error.remove(&idx);
+                }
+            }
+            let stabilizer_words = self.stabilizer_positions.len().div_ceil(64);
+            let observable_words = self.logical_observables.len().div_ceil(64);
+            let (mut syndrome, mut observables) =
+                (vec![0; stabilizer_words], vec![0; observable_words]);
+            for &idx in logical_error.iter() {
+                syndrome = xor(&syndrome, &mechanisms[idx].syndrome);
+                observables = xor(&observables, &mechanisms[idx].observables);
+            }
+            if syndrome.iter().all(|&word| word == 0) && observables.iter().any(|&word| word != 0) {
+                consider(&mut best, logical_error);
+            }
+        }
+        // breadth-first search
+        let root = SearchNode {
+            weight: 0,
+            parent: None,
+            syndrome: bitset(self.stabilizer_positions.len(), std::iter::empty()),
+            observables: bitset(self.logical_observables.len(), std::iter::empty()),
+        };
+        let mut buckets: HashMap<Vec<u64>, Vec<usize>> = HashMap::new();
+        buckets.insert(root.syndrome.clone(), vec![0]);
+        let mut nodes = vec![root];
+        let error_set = |nodes: &[SearchNode], mut node_idx: usize| {
+            let mut set = BTreeSet::new();
+            while let Some((parent, mechanism)) = nodes[node_idx].parent {
+                if !set.insert(mechanism) {
+                    set.remove(&mechanism);
+                }
+                node_idx = parent;
+            }
+            set
+        };
+        let mut frontier = vec![0];
+        let mut radius = 0; // all the sets of up to `radius` mechanisms have been explored
+        let mut is_exhausted = false;
+        'search: loop {
+            if best
+                .as_ref()
+                .is_some_and(|best| best.len() <= 2 * radius + 1)
+            {
+                break;
+            }
+            if frontier.is_empty() {
+                is_exhausted = true;
+                break;
+            }
+            let mut next_frontier = vec![];
+            for &node_idx in frontier.iter() {
+                for (mechanism_idx, mechanism) in mechanisms.iter().enumerate() {
+                    let syndrome = xor(&nodes[node_idx].syndrome, &mechanism.syndrome);
+                    let observables = xor(&nodes[node_idx].observables, &mechanism.observables);
+                    let bucket = buckets.entry(syndrome.clone()).or_default();
+                    if bucket
+                        .iter()
+                        .any(|&other| nodes[other].observables == observables)
+                    {
+                        continue; // reached by a set of no more mechanisms
+                    }
+                    let new_idx = nodes.len();
+                    nodes.push(SearchNode {
+                        weight: radius + 1,
+                        parent: Some((node_idx, mechanism_idx)),
+                        syndrome,
+                        observables,
+                    });
+                    for &other in bucket.iter() {
+                        if best
+                            .as_ref()
+                            .is_none_or(|best| nodes[other].weight + radius + 1 < best.len())
+                        {
+                            let logical_error: BTreeSet<usize> = error_set(&nodes, new_idx)
+                                .symmetric_difference(&error_set(&nodes, other))
+                                .cloned()
+                                .collect();
+                            consider(&mut best, logical_error);
+                        }
+                    }
+                    bucket.push(new_idx);
+                    next_frontier.push(new_idx);
+                    if nodes.len() >= max_states {
+                        break 'search;
+                    }
+                }
+            }
+            frontier = next_frontier;
+            radius += 1;
+        }
+        let Some(best) = best else {
+            if is_exhausted {
+                return Err("no combination of errors flips a logical observable".to_string());
+            }
+            return Ok(CodeDistance {
+                lower_bound: 2 * radius + 1,
+                upper_bound: None,
+                is_exact: false,
+                logical_error: vec![],
+                flipped_observables: vec![],
+                explored_states: nodes.len(),
+            });
+        };
+        let lower_bound = if is_exhausted {
+            best.len()
+        } else {
+            best.len().min(2 * radius + 1)
+        };
+        let mut observables = bitset(self.logical_observables.len(), std::iter::empty());
+        for &idx in best.iter() {
+            observables = xor(&observables, &mechanisms[idx].observables);
+        }
+        Ok(CodeDistance {
+            lower_bound,
+            upper_bound: Some(best.len()),
+            is_exact: lower_bound == best.len(),
+            logical_error: best
+                .iter()
+                .map(|&idx| {
+                    (
+                        mechanisms[idx].data_index,
+                        mechanisms[idx].error_type.clone(),
+                    )
+                })
+                .collect(),
+            flipped_observables: bitset_indices(&observables),
+            explored_states: nodes.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stabilizer::*;
+
+    #[test]
+    fn test_code_distance_builtin_codes() {
+        // cargo test -- test_code_distance_builtin_codes --nocapture
+        for d in [3, 5] {
+            let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(d, NoiseType::Depolarize));
+            let distance = code.client_info.code_distance(DEFAULT_MAX_STATES).unwrap();
+            println!("{:?}", distance);
+            assert!(distance.is_exact);
+            assert_eq!(distance.upper_bound, Some(d));
+            assert_eq!(distance.logical_error.len(), d);
+            let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(d, NoiseType::BitFlip));
+            let distance = code.client_info.code_distance(DEFAULT_MAX_STATES).unwrap();
+            assert_eq!((distance.lower_bound, distance.upper_bound), (d, Some(d)));
+            assert_eq!(distance.flipped_observables, vec![0]);
+            let code = ServerCodeInfo::from(&TriangularColorCodeBitFlip::new(d));
+            let distance = code.client_info.code_distance(DEFAULT_MAX_STATES).unwrap();
+            assert_eq!((distance.lower_bound, distance.upper_bound), (d, Some(d)));
+        }
+        // the Y-only variant has a different effective distance, which is verified by exhaustive search
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::OnlyY));
+        let distance = code.client_info.code_distance(DEFAULT_MAX_STATES).unwrap();
+        // a Y-only logical error must cover all the 9 data qubits
+        assert!(distance.is_exact);
+        assert_eq!((distance.lower_bound, distance.upper_bound), (9, Some(9)));
+    }
+
+    #[test]
+    fn test_code_distance_bounds() {
+        // cargo test -- test_code_distance_bounds --nocapture
+        let client_info = StabilizerCode {
+            stabilizers: ["XZZXI", "IXZZX", "XIXZZ", "ZXIXZ"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+        .build_client_info("five")
+        .unwrap();
+        let distance = client_info.code_distance(DEFAULT_MAX_STATES).unwrap();
+        assert!(distance.is_exact);
+        assert_eq!(distance.upper_bound, Some(3));
+        // a limited search gives bounds
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(7, NoiseType::Depolarize));
+        let distance = code.client_info.code_distance(100).unwrap();
+        assert!(!distance.is_exact);
+        assert!(distance.lower_bound < 7);
+        assert_eq!(distance.upper_bound, Some(7));
+    }
+}
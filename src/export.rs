@@ -0,0 +1,393 @@
+use crate::codes::*;
+use num_traits::cast::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// Stim detector error model, with the probability p = 1 / (1 + e^w) of each hyperedge
+    Dem,
+    Json,
+    /// Graphviz DOT, with each hyperedge drawn as a factor node connected to its vertices
+    Dot,
+    GraphMl,
+}
+
+impl GraphFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            GraphFormat::Dem => "text/plain",
+            GraphFormat::Json => "application/json",
+            GraphFormat::Dot => "text/vnd.graphviz",
+            GraphFormat::GraphMl => "application/xml",
+        }
+    }
+}
+
+/// a single line of text for the comments of a detector error model
+fn escape_dem_comment(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// the content of a quoted string of Graphviz DOT
+fn escape_dot(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the text or attribute value of XML
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // the control characters other than whitespace are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// a hyperedge of the decoding graph together with the physical error it stands for
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphHyperedge {
+    pub index: usize,
+    pub vertices: Vec<usize>,
+    pub weight: f64,
+    /// the probability of the error derived from the weight w = ln((1-p)/p)
+    pub probability: f64,
+    /// the representative error of the hyperedge
+    pub data_qubit: usize,
+    pub error_type: String,
+    /// all the errors merged into the hyperedge
+    pub equivalent_errors: Vec<(usize, String)>,
+    /// all the correlated errors merged into the hyperedge, as indices of `correlated_errors` of the code
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub correlated_errors: Vec<usize>,
+    /// the Pauli errors of the most probable error of the hyperedge
+    pub event: Vec<(usize, String)>,
+    /// the logical observables flipped by `event`
+    pub observables: Vec<usize>,
+}
+
+impl ServerCodeInfo {
+    /// the logical observables flipped by the most probable error of a hyperedge
+    pub fn edge_observables(&self, edge_index: usize) -> Vec<usize> {
+        let event = &self.edge_events[edge_index];
+        self.client_info
+            .logical_observables
+            .iter()
+            .enumerate()
+            .filter(|(_, observable)| {
+                let anticommuting = observable.iter().filter(|(data_index, check_type)| {
+                    event.iter().any(|(event_index, event_type)| {
+                        event_index == data_index && pauli_anticommute(event_type, check_type)
+                    })
+                });
+                anticommuting.count() % 2 == 1
+            })
+            .map(|(observable_idx, _)| observable_idx)
+            .collect()
+    }
+
+    /// the hyperedges of the decoding graph in the order of their edge indices
+    pub fn graph_hyperedges(&self) -> Vec<GraphHyperedge> {
+        self.solver_initializer
+            .weighted_edges
+            .iter()
+            .zip(self.edge_errors.iter())
+            .enumerate()
+            .map(|(index, (hyperedge, (data_qubit, error_type)))| {
+                let weight = hyperedge.weight.to_f64().unwrap();
+                GraphHyperedge {
+                    index,
+                    vertices: hyperedge.vertices.clone(),
+                    weight,
+                    probability: 1.0 / (1.0 + weight.exp()),
+                    data_qubit: *data_qubit,
+                    error_type: error_type.clone(),
+                    equivalent_errors: self.edge_equivalent_errors[index].clone(),
+                    correlated_errors: self.edge_correlated_errors[index].clone(),
+                    observables: self.edge_observables(index),
+                    event: self.edge_events[index].clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// serialize the decoding graph that the server decodes on
+    pub fn export_graph(&self, format: GraphFormat) -> String {
+        let hyperedges = self.graph_hyperedges();
+        let vertex_num = self.solver_initializer.vertex_num;
+        let mut output = String::new();
+        match format {
+            GraphFormat::Dem => {
+                writeln!(
+                    output,
+                    "# decoding graph of {}",
+                    escape_dem_comment(&self.client_info.name)
+                )
+                .unwrap();
+                for hyperedge in hyperedges.iter() {
+                    let mut targets: Vec<String> = hyperedge
+                        .vertices
+                        .iter()
+                        .map(|vertex| format!("D{}", vertex))
+                        .collect();
+                    targets.extend(
+                        hyperedge
+                            .observables
+                            .iter()
+                            .map(|observable| format!("L{}", observable)),
+                    );
+                    writeln!(
+                        output,
+                        "error({}) {} # data qubit {} {}",
+                        hyperedge.probability,
+                        targets.join(" "),
+                        hyperedge.data_qubit,
+                        escape_dem_comment(&hyperedge.error_type)
+                    )
+                    .unwrap();
+                }
+                // the original coordinates of a detector error model, or the unscaled positions of the stabilizers,
+                // rather than the visualizer positions, so that the model round-trips
+                for vertex in 0..vertex_num {
+                    let coordinates = if self.client_info.is_detector_error_model {
+                        self.client_info
+                            .detector_coordinates
+                            .get(vertex)
+                            .cloned()
+                            .unwrap_or_default()
+                    } else {
+                        let (i, j) = self.client_info.stabilizer_positions[vertex];
+                        vec![i, j, 0.0]
+                    };
+                    if coordinates.is_empty() {
+                        writeln!(output, "detector D{}", vertex).unwrap();
+                    } else {
+                        let coordinates: Vec<String> =
+                            coordinates.iter().map(|value| value.to_string()).collect();
+                        writeln!(output, "detector({}) D{}", coordinates.join(", "), vertex)
+                            .unwrap();
+                    }
+                }
+                for observable_idx in 0..self.client_info.logical_observables.len() {
+                    writeln!(output, "logical_observable L{}", observable_idx).unwrap();
+                }
+            }
+            GraphFormat::Json => {
+                output = json!({
+                    "code_id": self.client_info.id,
+                    "vertex_num": vertex_num,
+                    "vertex_positions": self
+                        .visualize_positions
+                        .iter()
+                        .map(|position| (position.i, position.j, position.t))
+                        .collect::<Vec<_>>(),
+                    "hyperedges": hyperedges,
+                })
+                .to_string();
+            }
+            GraphFormat::Dot => {
+                writeln!(output, "graph \"{}\" {{", escape_dot(&self.client_info.id)).unwrap();
+                for (vertex, position) in self.visualize_positions.iter().enumerate() {
+                    writeln!(
+                        output,
+                        "    v{} [label=\"D{}\", shape=circle, pos=\"{},{}\"];",
+                        vertex, vertex, position.j, -position.i
+                    )
+                    .unwrap();
+                }
+                for hyperedge in hyperedges.iter() {
+                    writeln!(
+                        output,
+                        "    e{} [label=\"{}{}\\nw={:.4}\", shape=box];",
+                        hyperedge.index,
+                        escape_dot(&hyperedge.error_type),
+                        hyperedge.data_qubit,
+                        hyperedge.weight
+                    )
+                    .unwrap();
+                    for vertex in hyperedge.vertices.iter() {
+                        writeln!(output, "    e{} -- v{};", hyperedge.index, vertex).unwrap();
+                    }
+                }
+                writeln!(output, "}}").unwrap();
+            }
+            GraphFormat::GraphMl => {
+                writeln!(output, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+                writeln!(
+                    output,
+                    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+                )
+                .unwrap();
+                for (key, kind, name, value_type) in [
+                    ("kind", "node", "kind", "string"),
+                    ("i", "node", "i", "double"),
+                    ("j", "node", "j", "double"),
+                    ("t", "node", "t", "double"),
+                    ("weight", "node", "weight", "double"),
+                    ("probability", "node", "probability", "double"),
+                    ("data_qubit", "node", "data_qubit", "int"),
+                    ("error_type", "node", "error_type", "string"),
+                    ("observables", "node", "observables", "string"),
+                ] {
+                    writeln!(
+                        output,
+                        "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                        key, kind, name, value_type
+                    )
+                    .unwrap();
+                }
+                writeln!(
+                    output,
+                    "  <graph id=\"{}\" edgedefault=\"undirected\">",
+                    escape_xml(&self.client_info.id)
+                )
+                .unwrap();
+                for (vertex, position) in self.visualize_positions.iter().enumerate() {
+                    writeln!(
+                        output,
+                        "    <node id=\"v{}\"><data key=\"kind\">vertex</data><data key=\"i\">{}</data><data key=\"j\">{}</data><data key=\"t\">{}</data></node>",
+                        vertex, position.i, position.j, position.t
+                    )
+                    .unwrap();
+                }
+                for hyperedge in hyperedges.iter() {
+                    writeln!(
+                        output,
+                        "    <node id=\"e{}\"><data key=\"kind\">hyperedge</data><data key=\"weight\">{}</data><data key=\"probability\">{}</data><data key=\"data_qubit\">{}</data><data key=\"error_type\">{}</data><data key=\"observables\">{}</data></node>",
+                        hyperedge.index,
+                        hyperedge.weight,
+                        hyperedge.probability,
+                        hyperedge.data_qubit,
+                        escape_xml(&hyperedge.error_type),
+                        hyperedge
+                            .observables
+                            .iter()
+                            .map(|observable| observable.to_string())
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    )
+                    .unwrap();
+                    for vertex in hyperedge.vertices.iter() {
+                        writeln!(
+                            output,
+                            "    <edge source=\"e{}\" target=\"v{}\"/>",
+                            hyperedge.index, vertex
+                        )
+                        .unwrap();
+                    }
+                }
+                writeln!(output, "  </graph>").unwrap();
+                writeln!(output, "</graphml>").unwrap();
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dem::DetectorErrorModel;
+
+    #[test]
+    fn test_export_graph() {
+        // cargo test -- test_export_graph --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let edge_num = code.solver_initializer.weighted_edges.len();
+        let dot = code.export_graph(GraphFormat::Dot);
+        assert_eq!(dot.matches("shape=box").count(), edge_num);
+        let graphml = code.export_graph(GraphFormat::GraphMl);
+        assert_eq!(
+            graphml.matches("<data key=\"kind\">hyperedge").count(),
+            edge_num
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&code.export_graph(GraphFormat::Json)).unwrap();
+        assert_eq!(json["hyperedges"].as_array().unwrap().len(), edge_num);
+        // the exported detector error model can be imported again
+        let dem = code.export_graph(GraphFormat::Dem);
+        let model = DetectorErrorModel::parse(&dem).unwrap();
+        assert_eq!(model.errors.len(), edge_num);
+        assert_eq!(model.detector_num, code.solver_initializer.vertex_num);
+        assert_eq!(model.observable_num, 2);
+    }
+
+    #[test]
+    fn test_export_dem_coordinates() {
+        // cargo test -- test_export_dem_coordinates --nocapture
+        let text = "
+            error(0.1) D0 D1 L0
+            error(0.1) D1 D2
+            detector(1, 0, 0) D0
+            detector(3.5, 0, 0, 7) D1
+            detector D2
+        ";
+        let model = DetectorErrorModel::parse(text).unwrap();
+        let code = model.build("dem-test", "DEM Test");
+        let dem = code.export_graph(GraphFormat::Dem);
+        // the exported model has the same detectors as the original one
+        let exported = DetectorErrorModel::parse(&dem).unwrap();
+        assert_eq!(exported.detector_coordinates, model.detector_coordinates);
+        assert_eq!(exported.detector_coordinates[2], None);
+        let rebuilt = exported.build("dem-test", "DEM Test");
+        assert_eq!(
+            DetectorErrorModel::parse(&rebuilt.export_graph(GraphFormat::Dem))
+                .unwrap()
+                .detector_coordinates,
+            model.detector_coordinates
+        );
+        // the other codes export the unscaled positions of their stabilizers
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let exported = DetectorErrorModel::parse(&code.export_graph(GraphFormat::Dem)).unwrap();
+        for (coordinates, (i, j)) in exported
+            .detector_coordinates
+            .iter()
+            .zip(code.client_info.stabilizer_positions.iter())
+        {
+            assert_eq!(coordinates, &Some(vec![*i, *j, 0.0]));
+        }
+    }
+
+    #[test]
+    fn test_export_graph_escape() {
+        // cargo test -- test_export_graph_escape --nocapture
+        let mut code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        code.client_info.name = "name\nerror(0.5) D0".to_string();
+        code.client_info.id = "id\" <graph> & \\".to_string();
+        let dem = code.export_graph(GraphFormat::Dem);
+        let model = DetectorErrorModel::parse(&dem).unwrap();
+        assert_eq!(
+            model.errors.len(),
+            code.solver_initializer.weighted_edges.len()
+        );
+        let dot = code.export_graph(GraphFormat::Dot);
+        assert!(dot.starts_with("graph \"id\\\" <graph> & \\\\\" {"));
+        let graphml = code.export_graph(GraphFormat::GraphMl);
+        assert!(graphml.contains("<graph id=\"id&quot; &lt;graph&gt; &amp; \\\""));
+        assert_eq!(graphml.matches("<graph ").count(), 1);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::codes::*;
+
+/// the average distance between a stabilizer and the data qubits it checks
+const LAYOUT_EDGE_LENGTH: f64 = 1.5;
+/// the margin of the stabilizer shapes around the qubits
+const LAYOUT_SHAPE_MARGIN: f64 = 0.3;
+const LAYOUT_SHAPE_SEGMENTS: usize = 8;
+const LAYOUT_MAX_ITERATIONS: usize = 300;
+/// fewer iterations do not converge, so larger codes are placed on a grid instead
+const LAYOUT_MIN_ITERATIONS: usize = 10;
+/// limit the total amount of work of the O(N^2) force-directed iterations
+const LAYOUT_MAX_WORK: usize = 20_000_000;
+
+/// the geometry of a code computed by [`auto_layout`]
+#[derive(Debug, Clone)]
+pub struct CodeLayout {
+    pub data_qubit_positions: Vec<(f64, f64)>,
+    pub stabilizer_positions: Vec<(f64, f64)>,
+    pub stabilizer_shapes: Vec<Vec<(f64, f64)>>,
+}
+
+/// place the nodes of the Tanner graph (data qubits followed by stabilizers) with the Fruchterman-Reingold
+/// force-directed algorithm, starting from a deterministic placement on a circle
+fn force_directed(node_num: usize, edges: &[(usize, usize)]) -> Vec<(f64, f64)> {
+    let mut positions: Vec<(f64, f64)> = (0..node_num)
+        .map(|node| {
+            let theta = 2.0 * std::f64::consts::PI * node as f64 / node_num as f64;
+            (theta.cos(), theta.sin())
+        })
+        .collect();
+    if node_num <= 1 {
+        return positions;
+    }
+    let k = 1.0 / (node_num as f64).sqrt();
+    let iterations = (LAYOUT_MAX_WORK / (node_num * node_num)).min(LAYOUT_MAX_ITERATIONS);
+    for iteration in 0..iterations {
+        let temperature = 0.1 * (1.0 - iteration as f64 / iterations as f64);
+        let mut displacements = vec![(0.0, 0.0); node_num];
+        for a in 0..node_num {
+            for b in (a + 1)..node_num {
+                let (di, dj) = (
+                    positions[a].0 - positions[b].0,
+                    positions[a].1 - positions[b].1,
+                );
+                let distance = (di * di + dj * dj).sqrt().max(1e-6);
+                let force = k * k / distance;
+                displacements[a].0 += di / distance * force;
+                displacements[a].1 += dj / distance * force;
+                displacements[b].0 -= di / distance * force;
+                displacements[b].1 -= dj / distance * force;
+            }
+        }
+        for &(a, b) in edges.iter() {
+            let (di, dj) = (
+                positions[a].0 - positions[b].0,
+                positions[a].1 - positions[b].1,
+            );
+            let distance = (di * di + dj * dj).sqrt().max(1e-6);
+            let force = distance * distance / k;
+            displacements[a].0 -= di / distance * force;
+            displacements[a].1 -= dj / distance * force;
+            displacements[b].0 += di / distance * force;
+            displacements[b].1 += dj / distance * force;
+        }
+        for (position, (di, dj)) in positions.iter_mut().zip(displacements) {
+            let length = (di * di + dj * dj).sqrt().max(1e-9);
+            let step = length.min(temperature);
+            position.0 += di / length * step;
+            position.1 += dj / length * step;
+        }
+    }
+    positions
+}
+
+/// place the data qubits row by row on a square grid and each stabilizer at the center of the data qubits it checks,
+/// shifted by a small amount so that the stabilizers checking the same data qubits do not overlap;
+/// it takes linear time for the codes too large for [`force_directed`]
+fn grid_layout(
+    data_qubit_num: usize,
+    node_num: usize,
+    edges: &[(usize, usize)],
+) -> Vec<(f64, f64)> {
+    let width = (data_qubit_num as f64).sqrt().ceil().max(1.0) as usize;
+    let mut positions: Vec<(f64, f64)> = (0..data_qubit_num)
+        .map(|data_index| ((data_index / width) as f64, (data_index % width) as f64))
+        .collect();
+    let mut sums = vec![(0.0, 0.0, 0usize); node_num - data_qubit_num];
+    for &(data_index, node) in edges.iter() {
+        let sum = &mut sums[node - data_qubit_num];
+        sum.0 += positions[data_index].0;
+        sum.1 += positions[data_index].1;
+        sum.2 += 1;
+    }
+    for (stabilizer_idx, (i, j, count)) in sums.into_iter().enumerate() {
+        let theta = stabilizer_idx as f64;
+        let shift = (0.3 * theta.cos(), 0.3 * theta.sin());
+        positions.push(if count == 0 {
+            // a stabilizer without data qubits goes below the grid
+            (
+                (width + stabilizer_idx / width) as f64,
+                (stabilizer_idx % width) as f64,
+            )
+        } else {
+            (i / count as f64 + shift.0, j / count as f64 + shift.1)
+        });
+    }
+    positions
+}
+
+/// the convex hull of the points in counter-clockwise order, using Andrew's monotone chain algorithm
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Vec<(f64, f64)> = if pass == 0 {
+            points.clone()
+        } else {
+            points.iter().rev().cloned().collect()
+        };
+        for point in ordered {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop(); // the last point is the first point of the other pass
+    }
+    hull
+}
+
+/// a shape around the stabilizer and the data qubits it checks, as the convex hull of small circles around them
+pub fn support_shape(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut inflated = vec![];
+    for &(i, j) in points.iter() {
+        for segment in 0..LAYOUT_SHAPE_SEGMENTS {
+            let theta = 2.0 * std::f64::consts::PI * segment as f64 / LAYOUT_SHAPE_SEGMENTS as f64;
+            inflated.push((
+                i + LAYOUT_SHAPE_MARGIN * theta.cos(),
+                j + LAYOUT_SHAPE_MARGIN * theta.sin(),
+            ));
+        }
+    }
+    convex_hull(inflated)
+}
+
+/// compute the positions of data qubits and stabilizers of a code without natural geometry
+/// from its Tanner graph, and the shapes of the stabilizers around the data qubits they check;
+/// the out-of-range data qubits in the checks are ignored
+pub fn auto_layout(
+    data_qubit_num: usize,
+    stabilizer_checks: &[Vec<(usize, String)>],
+) -> CodeLayout {
+    let node_num = data_qubit_num + stabilizer_checks.len();
+    let edges: Vec<(usize, usize)> = stabilizer_checks
+        .iter()
+        .enumerate()
+        .flat_map(|(stabilizer_idx, check)| {
+            check
+                .iter()
+                .filter(|(data_index, _)| *data_index < data_qubit_num)
+                .map(move |(data_index, _)| (*data_index, data_qubit_num + stabilizer_idx))
+        })
+        .collect();
+    let mut positions = if node_num
+        .saturating_mul(node_num)
+        .saturating_mul(LAYOUT_MIN_ITERATIONS)
+        > LAYOUT_MAX_WORK
+    {
+        grid_layout(data_qubit_num, node_num, &edges)
+    } else {
+        force_directed(node_num, &edges)
+    };
+    // rescale so that the average length of the edges in the Tanner graph is fixed
+    let average_length = if edges.is_empty() {
+        1.0
+    } else {
+        edges
+            .iter()
+            .map(|&(a, b)| {
+                ((positions[a].0 - positions[b].0).powi(2)
+                    + (positions[a].1 - positions[b].1).powi(2))
+                .sqrt()
+            })
+            .sum::<f64>()
+            / edges.len() as f64
+    };
+    let scale = LAYOUT_EDGE_LENGTH / average_length.max(1e-9);
+    for position in positions.iter_mut() {
+        *position = (position.0 * scale, position.1 * scale);
+    }
+    let stabilizer_positions = positions.split_off(data_qubit_num);
+    let data_qubit_positions = positions;
+    let stabilizer_shapes = stabilizer_checks
+        .iter()
+        .zip(stabilizer_positions.iter())
+        .map(|(check, &position)| {
+            let mut points = vec![position];
+            points.extend(
+                check
+                    .iter()
+                    .filter_map(|(data_index, _)| data_qubit_positions.get(*data_index).cloned()),
+            );
+            support_shape(&points)
+        })
+        .collect();
+    CodeLayout {
+        data_qubit_positions,
+        stabilizer_positions,
+        stabilizer_shapes,
+    }
+}
+
+/// the color of a stabilizer by its type: blue for X, green for Z and red for the others
+pub fn stabilizer_color(check: &[(usize, String)]) -> String {
+    if !check.is_empty() && check.iter().all(|(_, check_type)| check_type == "X") {
+        BLUE.to_string()
+    } else if !check.is_empty() && check.iter().all(|(_, check_type)| check_type == "Z") {
+        GREEN.to_string()
+    } else {
+        RED.to_string()
+    }
+}
+
+impl ClientCodeInfo {
+    /// replace the positions and shapes with an automatic layout, and fill in the missing colors
+    pub fn apply_auto_layout(&mut self) {
+        let layout = auto_layout(self.data_qubit_actions.len(), &self.stabilizer_checks);
+        self.data_qubit_positions = layout.data_qubit_positions;
+        self.stabilizer_positions = layout.stabilizer_positions;
+        self.stabilizer_shapes = layout.stabilizer_shapes;
+        if self.stabilizer_colors.len() != self.stabilizer_checks.len() {
+            self.stabilizer_colors = self
+                .stabilizer_checks
+                .iter()
+                .map(|check| stabilizer_color(check))
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parity_check::*;
+
+    #[test]
+    fn test_auto_layout() {
+        // cargo test -- test_auto_layout --nocapture
+        let code = ParityCheckCode {
+            h: Some(BinaryMatrix::Sparse {
+                columns: 5,
+                rows: vec![vec![0, 1], vec![1, 2], vec![2, 3], vec![3, 4]],
+            }),
+            ..Default::default()
+        };
+        let client_info = code.build_client_info("repetition").unwrap();
+        let layout = auto_layout(5, &client_info.stabilizer_checks);
+        assert_eq!(layout.data_qubit_positions.len(), 5);
+        assert_eq!(layout.stabilizer_positions.len(), 4);
+        for shape in layout.stabilizer_shapes.iter() {
+            assert!(shape.len() >= 3);
+        }
+        // the layout is deterministic
+        let layout_2 = auto_layout(5, &client_info.stabilizer_checks);
+        assert_eq!(layout.data_qubit_positions, layout_2.data_qubit_positions);
+        // the neighboring qubits are closer than the qubits at both ends of the chain
+        let distance =
+            |a: (f64, f64), b: (f64, f64)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+        let positions = &layout.data_qubit_positions;
+        assert!(distance(positions[0], positions[1]) < distance(positions[0], positions[4]));
+    }
+
+    #[test]
+    fn test_auto_layout_large() {
+        // cargo test -- test_auto_layout_large --nocapture
+        let n = 3000;
+        let stabilizer_checks: Vec<Vec<(usize, String)>> = (0..n - 1)
+            .map(|i| vec![(i, "Z".to_string()), (i + 1, "Z".to_string())])
+            .collect();
+        // too large for the force-directed layout, which would take longer than the work limit
+        let node_num = 2 * n - 1;
+        assert!(node_num * node_num * LAYOUT_MIN_ITERATIONS > LAYOUT_MAX_WORK);
+        let layout = auto_layout(n, &stabilizer_checks);
+        assert_eq!(layout.data_qubit_positions.len(), n);
+        assert_eq!(layout.stabilizer_positions.len(), n - 1);
+        // so the data qubits are placed row by row on the grid, up to the scale
+        let width = (n as f64).sqrt().ceil() as usize;
+        let step = layout.data_qubit_positions[1].1;
+        assert!(step > 0.0);
+        for (data_index, position) in layout.data_qubit_positions.iter().enumerate() {
+            assert!((position.0 - (data_index / width) as f64 * step).abs() < 1e-9);
+            assert!((position.1 - (data_index % width) as f64 * step).abs() < 1e-9);
+        }
+        for position in layout
+            .data_qubit_positions
+            .iter()
+            .chain(layout.stabilizer_positions.iter())
+        {
+            assert!(position.0.is_finite() && position.1.is_finite());
+        }
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        // cargo test -- test_convex_hull --nocapture
+        let hull = convex_hull(vec![
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (0.5, 0.2),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ]);
+        assert_eq!(hull, vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+    }
+}
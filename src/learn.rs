@@ -0,0 +1,340 @@
+use crate::codes::*;
+use mwpf::util::{HyperEdge, SolverInitializer};
+use num_traits::cast::ToPrimitive;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// the probabilities are clamped to this, so that the hyperedges never seen keep a finite weight
+const MIN_LEARNED_PROBABILITY: f64 = 1e-9;
+/// the maximum number of iterations of the least squares solver per hyperedge
+const LEAST_SQUARES_ITERATIONS_PER_EDGE: usize = 10;
+/// the parities of all the subsets of the hyperedges up to this degree are measured
+const MAX_SUBSET_DEGREE: usize = 6;
+/// the maximum number of (shot, stabilizer) lookups to measure the parities, about a few seconds of work
+pub const MAX_LEARN_WORK: usize = 1_000_000_000;
+
+/// a hyperedge with its probability estimated from the syndromes
+#[derive(Debug, Clone, Serialize)]
+pub struct LearnedHyperedge {
+    pub index: usize,
+    pub vertices: Vec<usize>,
+    /// the probability implied by the weight of the code
+    pub prior_probability: f64,
+    pub probability: f64,
+    pub weight: f64,
+    /// the center of the stabilizers of the hyperedge, for drawing a heatmap
+    pub position: (f64, f64),
+    /// whether no syndrome statistics constrain the hyperedge, so that its prior is kept
+    pub is_prior: bool,
+}
+
+/// a noise model estimated from a dataset of syndromes
+#[derive(Debug, Clone, Serialize)]
+pub struct LearnedNoiseModel {
+    pub code_id: String,
+    pub shots: usize,
+    /// the fraction of shots in which each stabilizer is a defect
+    pub defect_rates: Vec<f64>,
+    pub stabilizer_positions: Vec<(f64, f64)>,
+    pub hyperedges: Vec<LearnedHyperedge>,
+    /// the decoding hypergraph of the code with the learned weights, keeping the edge indices
+    pub solver_initializer: SolverInitializer,
+}
+
+/// parse a dataset of syndromes with one shot per line, each line listing the defect vertices as "1,2,3";
+/// an empty line is a shot without defects
+pub fn parse_syndromes(content: &str, vertex_num: usize) -> Result<Vec<Vec<usize>>, String> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(line_idx, line)| {
+            let line = line.trim();
+            if line.is_empty() {
+                return Ok(vec![]);
+            }
+            line.split(',')
+                .map(|vertex| match vertex.trim().parse::<usize>() {
+                    Ok(vertex) if vertex < vertex_num => Ok(vertex),
+                    _ => Err(format!(
+                        "invalid defect vertex {} at line {}",
+                        vertex,
+                        line_idx + 1
+                    )),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// solve min |A x - b| by conjugate gradient on the normal equations, where each row of A lists its nonzero
+/// (unit) columns
+fn least_squares(rows: &[(Vec<usize>, f64)], column_num: usize, max_iterations: usize) -> Vec<f64> {
+    let multiply = |x: &[f64]| -> Vec<f64> {
+        rows.iter()
+            .map(|(columns, _)| columns.iter().map(|&column| x[column]).sum())
+            .collect()
+    };
+    let multiply_transpose = |y: &[f64]| -> Vec<f64> {
+        let mut result = vec![0.0; column_num];
+        for ((columns, _), value) in rows.iter().zip(y.iter()) {
+            for &column in columns.iter() {
+                result[column] += value;
+            }
+        }
+        result
+    };
+    let norm_squared = |v: &[f64]| -> f64 { v.iter().map(|value| value * value).sum() };
+    let mut x = vec![0.0; column_num];
+    let mut residual: Vec<f64> = rows.iter().map(|(_, b)| *b).collect();
+    let mut gradient = multiply_transpose(&residual);
+    let mut direction = gradient.clone();
+    let mut gamma = norm_squared(&gradient);
+    let tolerance = 1e-24 * gamma.max(1.0);
+    for _ in 0..max_iterations {
+        if gamma <= tolerance {
+            break;
+        }
+        let q = multiply(&direction);
+        let alpha = gamma / norm_squared(&q);
+        for (x, d) in x.iter_mut().zip(direction.iter()) {
+            *x += alpha * d;
+        }
+        for (r, q) in residual.iter_mut().zip(q.iter()) {
+            *r -= alpha * q;
+        }
+        gradient = multiply_transpose(&residual);
+        let gamma_new = norm_squared(&gradient);
+        for (d, g) in direction.iter_mut().zip(gradient.iter()) {
+            *d = g + gamma_new / gamma * *d;
+        }
+        gamma = gamma_new;
+    }
+    x
+}
+
+impl ServerCodeInfo {
+    /// estimate the probability of each hyperedge from the syndromes of many shots, assuming that the hyperedges
+    /// flip independently. For any set S of stabilizers, E[(-1)^(number of defects in S)] is the product of
+    /// 1 - 2 p_e over the hyperedges e with an odd number of vertices in S, so its logarithm is linear in
+    /// ln(1 - 2 p_e); taking S as every single stabilizer and every subset of the vertices of each hyperedge gives
+    /// a linear system, solved in the least squares sense. For a graph this reduces to the well-known pairwise formula
+    /// p_ij = 1/2 - 1/2 sqrt(1 - 4 (<x_i x_j> - <x_i><x_j>) / (1 - 2 <x_i> - 2 <x_j> + 4 <x_i x_j>)), and it
+    /// applies likewise to the higher-order correlations of hyperedges
+    pub fn learn_noise_model(&self, syndromes: &[Vec<usize>]) -> Result<LearnedNoiseModel, String> {
+        if syndromes.is_empty() {
+            return Err("no syndrome to learn from".to_string());
+        }
+        let vertex_num = self.solver_initializer.vertex_num;
+        let hyperedges = &self.solver_initializer.weighted_edges;
+        let shots = syndromes.len();
+        // the sets of stabilizers whose parities are measured
+        let mut stabilizer_sets: BTreeSet<Vec<usize>> =
+            (0..vertex_num).map(|vertex| vec![vertex]).collect();
+        for hyperedge in hyperedges.iter() {
+            let mut vertices = hyperedge.vertices.clone();
+            vertices.sort();
+            if vertices.len() <= MAX_SUBSET_DEGREE {
+                for mask in 1..(1usize << vertices.len()) {
+                    stabilizer_sets.insert(
+                        (0..vertices.len())
+                            .filter(|bit| (mask >> bit) & 1 == 1)
+                            .map(|bit| vertices[bit])
+                            .collect(),
+                    );
+                }
+            } else {
+                stabilizer_sets.insert(vertices);
+            }
+        }
+        let work = stabilizer_sets
+            .iter()
+            .map(|stabilizer_set| stabilizer_set.len())
+            .sum::<usize>()
+            .saturating_mul(shots);
+        if work > MAX_LEARN_WORK {
+            return Err(format!(
+                "{} shots over {} sets of stabilizers take {} lookups, more than {}; use fewer shots",
+                shots,
+                stabilizer_sets.len(),
+                work,
+                MAX_LEARN_WORK
+            ));
+        }
+        let words = vertex_num.div_ceil(64);
+        let mut defect_counts = vec![0usize; vertex_num];
+        let shot_bits: Vec<Vec<u64>> = syndromes
+            .iter()
+            .map(|syndrome| {
+                let mut bits = vec![0u64; words];
+                for &vertex in syndrome.iter() {
+                    if vertex >= vertex_num {
+                        return Err(format!("defect vertex {} out of range", vertex));
+                    }
+                    bits[vertex / 64] ^= 1u64 << (vertex % 64);
+                }
+                for (vertex, count) in defect_counts.iter_mut().enumerate() {
+                    *count += ((bits[vertex / 64] >> (vertex % 64)) & 1) as usize;
+                }
+                Ok(bits)
+            })
+            .collect::<Result<_, String>>()?;
+        let mut incident_edges: Vec<Vec<usize>> = vec![vec![]; vertex_num];
+        for (edge_index, hyperedge) in hyperedges.iter().enumerate() {
+            for &vertex in hyperedge.vertices.iter() {
+                incident_edges[vertex].push(edge_index);
+            }
+        }
+        let mut rows = vec![];
+        let mut is_constrained = vec![false; hyperedges.len()];
+        for stabilizer_set in stabilizer_sets {
+            let odd_shots = shot_bits
+                .iter()
+                .filter(|bits| {
+                    stabilizer_set
+                        .iter()
+                        .filter(|&&vertex| (bits[vertex / 64] >> (vertex % 64)) & 1 == 1)
+                        .count()
+                        % 2
+                        == 1
+                })
+                .count();
+            let expectation = 1.0 - 2.0 * odd_shots as f64 / shots as f64;
+            if expectation <= 0.0 {
+                continue; // too noisy to take the logarithm
+            }
+            let candidates: BTreeSet<usize> = stabilizer_set
+                .iter()
+                .flat_map(|&vertex| incident_edges[vertex].iter().cloned())
+                .collect();
+            let columns: Vec<usize> = candidates
+                .into_iter()
+                .filter(|&edge_index| {
+                    hyperedges[edge_index]
+                        .vertices
+                        .iter()
+                        .filter(|vertex| stabilizer_set.binary_search(vertex).is_ok())
+                        .count()
+                        % 2
+                        == 1
+                })
+                .collect();
+            for &column in columns.iter() {
+                is_constrained[column] = true;
+            }
+            rows.push((columns, expectation.ln()));
+        }
+        let solution = least_squares(
+            &rows,
+            hyperedges.len(),
+            LEAST_SQUARES_ITERATIONS_PER_EDGE * hyperedges.len().max(1),
+        );
+        let mut learned_hyperedges = vec![];
+        let mut weighted_edges = vec![];
+        for (edge_index, hyperedge) in hyperedges.iter().enumerate() {
+            let prior_weight = hyperedge.weight.to_f64().unwrap();
+            let prior_probability = 1.0 / (1.0 + prior_weight.exp());
+            let (probability, weight) = if is_constrained[edge_index] {
+                // ln(1 - 2p) <= 0, i.e. p in [0, 0.5)
+                let probability = ((1.0 - solution[edge_index].min(0.0).exp()) / 2.0)
+                    .max(MIN_LEARNED_PROBABILITY);
+                (probability, ((1.0 - probability) / probability).ln())
+            } else {
+                (prior_probability, prior_weight)
+            };
+            let positions: Vec<(f64, f64)> = hyperedge
+                .vertices
+                .iter()
+                .map(|&vertex| self.client_info.stabilizer_positions[vertex])
+                .collect();
+            let vertices_len = positions.len().max(1) as f64;
+            learned_hyperedges.push(LearnedHyperedge {
+                index: edge_index,
+                vertices: hyperedge.vertices.clone(),
+                prior_probability,
+                probability,
+                weight,
+                position: (
+                    positions.iter().map(|(x, _)| x).sum::<f64>() / vertices_len,
+                    positions.iter().map(|(_, y)| y).sum::<f64>() / vertices_len,
+                ),
+                is_prior: !is_constrained[edge_index],
+            });
+            weighted_edges.push(HyperEdge::new(hyperedge.vertices.clone(), weight.into()));
+        }
+        Ok(LearnedNoiseModel {
+            code_id: self.client_info.id.clone(),
+            shots,
+            defect_rates: defect_counts
+                .iter()
+                .map(|&count| count as f64 / shots as f64)
+                .collect(),
+            stabilizer_positions: self.client_info.stabilizer_positions.clone(),
+            hyperedges: learned_hyperedges,
+            solver_initializer: SolverInitializer::new(vertex_num, weighted_edges),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learn_noise_model() {
+        // cargo test -- test_learn_noise_model --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let hyperedges = &code.solver_initializer.weighted_edges;
+        let probabilities: Vec<f64> = (0..hyperedges.len())
+            .map(|edge_index| 0.01 + 0.01 * (edge_index % 5) as f64)
+            .collect();
+        // sample the hyperedges independently with a deterministic generator
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let syndromes: Vec<Vec<usize>> = (0..100_000)
+            .map(|_| {
+                let mut defects = BTreeSet::new();
+                for (hyperedge, probability) in hyperedges.iter().zip(probabilities.iter()) {
+                    if random() < *probability {
+                        for &vertex in hyperedge.vertices.iter() {
+                            if !defects.insert(vertex) {
+                                defects.remove(&vertex);
+                            }
+                        }
+                    }
+                }
+                defects.into_iter().collect()
+            })
+            .collect();
+        let content: String = syndromes
+            .iter()
+            .map(|syndrome| {
+                let vertices: Vec<String> = syndrome.iter().map(|v| v.to_string()).collect();
+                vertices.join(",") + "\n"
+            })
+            .collect();
+        let syndromes = parse_syndromes(&content, code.solver_initializer.vertex_num).unwrap();
+        let model = code.learn_noise_model(&syndromes).unwrap();
+        assert_eq!(model.shots, 100_000);
+        for (hyperedge, probability) in model.hyperedges.iter().zip(probabilities.iter()) {
+            assert!(!hyperedge.is_prior);
+            assert!(
+                (hyperedge.probability - probability).abs() < 0.01,
+                "hyperedge {} has probability {} but learned {}",
+                hyperedge.index,
+                probability,
+                hyperedge.probability
+            );
+        }
+        assert!(parse_syndromes("0,1\n99\n", code.solver_initializer.vertex_num).is_err());
+        assert!(code.learn_noise_model(&[]).is_err());
+        // too many shots for the parities of a large code
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(25, NoiseType::Depolarize));
+        let error = code.learn_noise_model(&vec![vec![]; 200_000]).unwrap_err();
+        assert!(error.contains("fewer shots"), "{}", error);
+    }
+}
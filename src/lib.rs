@@ -1 +1,20 @@
+pub mod batch;
+pub mod cache;
+pub mod calibration;
 pub mod codes;
+pub mod dem;
+pub mod distance;
+pub mod export;
+pub mod layout;
+pub mod learn;
+pub mod limit;
+pub mod metadata;
+pub mod parity_check;
+pub mod preprocess;
+pub mod registry;
+pub mod solver;
+pub mod stabilizer;
+pub mod stim;
+pub mod symplectic;
+pub mod validate;
+pub mod weights;
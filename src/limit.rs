@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// a limit on the number of units of work, e.g. threads, running at the same time across all requests;
+/// it never blocks, so a request over the limit is rejected instead of waiting
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    running: AtomicUsize,
+    max: usize,
+}
+
+/// the units of work taken from a [`ConcurrencyLimit`], which are given back when dropped
+#[derive(Debug)]
+pub struct ConcurrencyPermit<'a> {
+    limit: &'a ConcurrencyLimit,
+    count: usize,
+}
+
+impl ConcurrencyLimit {
+    pub const fn new(max: usize) -> Self {
+        Self {
+            running: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// take as many as `count` units, but at least one; `None` if none is available
+    pub fn try_acquire(&self, count: usize) -> Option<ConcurrencyPermit<'_>> {
+        let mut running = self.running.load(Ordering::Relaxed);
+        loop {
+            let count = count.min(self.max.saturating_sub(running));
+            if count == 0 {
+                return None;
+            }
+            match self.running.compare_exchange_weak(
+                running,
+                running + count,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(ConcurrencyPermit { limit: self, count }),
+                Err(actual) => running = actual,
+            }
+        }
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+impl ConcurrencyPermit<'_> {
+    /// the number of units taken, which may be fewer than requested
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        self.limit.running.fetch_sub(self.count, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency_limit() {
+        // cargo test -- test_concurrency_limit --nocapture
+        let limit = ConcurrencyLimit::new(4);
+        let permit = limit.try_acquire(3).unwrap();
+        assert_eq!(permit.count(), 3);
+        // only the remaining units are given
+        let other = limit.try_acquire(3).unwrap();
+        assert_eq!(other.count(), 1);
+        assert!(limit.try_acquire(1).is_none());
+        drop(permit);
+        assert_eq!(limit.running(), 1);
+        assert_eq!(limit.try_acquire(usize::MAX).unwrap().count(), 3);
+        drop(other);
+        assert_eq!(limit.running(), 0);
+    }
+}
@@ -1,18 +1,33 @@
-pub mod codes;
-
-use actix_web::{App, HttpRequest, HttpServer, Responder, Result, get, web};
-use clap::Parser;
-use codes::*;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, Responder, Result, get, post, web};
+use clap::{Parser, Subcommand};
 use lazy_static::lazy_static;
-use mwpf::mwpf_solver::{SolverSerialJointSingleHair, SolverTrait};
 use mwpf::util::*;
 use mwpf::visualize::*;
+use mwpf_web::batch::*;
+use mwpf_web::cache::*;
+use mwpf_web::calibration::*;
+use mwpf_web::codes::*;
+use mwpf_web::dem::*;
+use mwpf_web::distance::*;
+use mwpf_web::export::*;
+use mwpf_web::learn::*;
+use mwpf_web::limit::*;
+use mwpf_web::metadata::*;
+use mwpf_web::parity_check::*;
+use mwpf_web::preprocess::*;
+use mwpf_web::registry::*;
+use mwpf_web::solver::*;
+use mwpf_web::stabilizer::*;
+use mwpf_web::weights::*;
 use num_traits::One;
 use num_traits::cast::ToPrimitive;
 use serde::Deserialize;
 use serde_json::json;
 use std::collections::HashMap;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 #[derive(Debug, Deserialize)]
 pub struct DecodeParams {
@@ -28,34 +43,149 @@ pub struct DecodeParams {
     /// if not specified, the default value is 200
     #[serde(default = "default_cluster_node_limit")]
     pub cluster_node_limit: usize,
+    /// the solver: union-find, single-hair or joint-single-hair (default)
+    #[serde(default)]
+    pub solver: SolverType,
+    /// the JSON config of the solver, e.g. {"timeout":1}, limited to the options allowed for the solver
+    pub solver_config: Option<String>,
+    /// if specified, the decoding hypergraph is simplified before solving, see [`preprocess`]
+    pub preprocess: Option<String>,
+    /// the erased data qubits in the format of "1,2,3", on which any error is free
+    pub erasures: Option<String>,
+    /// the erased hyperedges in the format of "1,2,3", i.e. the indices in `correction_edges`
+    pub erased_edges: Option<String>,
+    /// the error probabilities for this decoding only, in the format of "4:X:0.01,5:Z:0.02"
+    pub error_probabilities: Option<String>,
+    /// the hyperedge weights for this decoding only, in the format of "3:0.5,7:2.1"
+    pub edge_weights: Option<String>,
+    /// the confidence of each stabilizer measurement in [0.5, 1], in the format of "0.99,0.9,..."
+    pub measurement_confidences: Option<String>,
+    /// the log-likelihood ratio of each stabilizer measurement, in the format of "4.6,2.2,..."
+    pub measurement_llrs: Option<String>,
 }
 
 fn default_cluster_node_limit() -> usize {
     200
 }
 
+/// the JSON body of a decoding request, carrying the same options as [`DecodeParams`] in their native types
+#[derive(Debug, Clone, Deserialize)]
+pub struct DecodeRequest {
+    /// the id of the code type
+    pub code_id: String,
+    /// the defect vertices
+    pub syndrome: Vec<usize>,
+    /// if true, the visualizer json will be returned in the field `json`
+    #[serde(default)]
+    pub with_json: bool,
+    /// if true, the standalone html visualizer will be returned in the field `html`
+    #[serde(default)]
+    pub with_html: bool,
+    /// the maximum number of nodes per cluster
+    #[serde(default = "default_cluster_node_limit")]
+    pub cluster_node_limit: usize,
+    /// the solver
+    #[serde(default)]
+    pub solver: SolverType,
+    /// the config of the solver, limited to the options allowed for the solver
+    pub solver_config: Option<serde_json::Value>,
+    /// if true, the decoding hypergraph is simplified before solving, see [`preprocess`]
+    #[serde(default)]
+    pub preprocess: bool,
+    /// the erased data qubits, on which any error is free
+    #[serde(default)]
+    pub erasures: Vec<usize>,
+    /// the erased hyperedges, i.e. the indices in `correction_edges`
+    #[serde(default)]
+    pub erased_edges: Vec<usize>,
+    /// the error probabilities and hyperedge weights for this decoding only
+    #[serde(flatten)]
+    pub overrides: WeightOverrides,
+    /// the confidence of each stabilizer measurement in [0.5, 1]
+    pub measurement_confidences: Option<Vec<f64>>,
+    /// the log-likelihood ratio of each stabilizer measurement
+    pub measurement_llrs: Option<Vec<f64>>,
+}
+
+impl DecodeParams {
+    /// parse the query string into a decoding request
+    pub fn to_request(&self) -> Result<DecodeRequest> {
+        let indices = |list: &Option<String>, name: &str| match list {
+            Some(list) => parse_indices(list, name),
+            None => Ok(vec![]),
+        };
+        Ok(DecodeRequest {
+            code_id: self.code_id.clone(),
+            syndrome: parse_indices(&self.syndrome, "syndrome")?,
+            with_json: self.with_json.is_some(),
+            with_html: self.with_html.is_some(),
+            cluster_node_limit: self.cluster_node_limit,
+            solver: self.solver,
+            solver_config: parse_solver_config(self.solver_config.as_deref())?,
+            preprocess: self.preprocess.is_some(),
+            erasures: indices(&self.erasures, "erasures")?,
+            erased_edges: indices(&self.erased_edges, "erased edges")?,
+            overrides: WeightOverrides::parse(
+                self.error_probabilities.as_deref(),
+                self.edge_weights.as_deref(),
+            )
+            .map_err(actix_web::error::ErrorBadRequest)?,
+            // the confidences are already converted into LLRs
+            measurement_confidences: None,
+            measurement_llrs: parse_measurement_llrs(
+                self.measurement_confidences.as_deref(),
+                self.measurement_llrs.as_deref(),
+            )
+            .map_err(actix_web::error::ErrorBadRequest)?,
+        })
+    }
+}
+
 pub struct DecodeResult {
     pub correction: Vec<(usize, String)>,
+    /// the stable indices of the hyperedges in the correction, in the same order as `correction`
+    pub correction_edges: Vec<usize>,
+    /// all the errors merged into each hyperedge in the correction, among which `correction` has the representatives
+    pub equivalent_errors: Vec<Vec<(usize, String)>>,
+    /// the Pauli errors of the most probable error of each hyperedge in the correction, which act on several data
+    /// qubits for a correlated error
+    pub correction_events: Vec<Vec<(usize, String)>>,
+    /// the correlated errors merged into each hyperedge in the correction
+    pub correlated_errors: Vec<Vec<usize>>,
+    /// the hyperedges with zero weight due to erasures
+    pub erasures: Vec<usize>,
+    /// the stabilizers whose measurements are flipped in the correction, given soft information
+    pub measurement_errors: Vec<usize>,
     pub weight_range: WeightRange,
     pub visualizer: Option<Visualizer>,
 }
 
-pub async fn decode_common(query: &DecodeParams) -> Result<DecodeResult> {
-    let code_id = query.code_id.clone();
-    let code = CODES_MAP
-        .get(&code_id)
-        .ok_or(actix_web::error::ErrorBadRequest(
-            "Code not found".to_string(),
-        ))?;
-    let syndrome: Vec<usize> = query
-        .syndrome
-        .split(',')
+/// parse a list of indices in the format of "1,2,3"
+fn parse_indices(list: &str, name: &str) -> Result<Vec<usize>> {
+    list.split(',')
         .map(|s| {
-            s.parse::<usize>().map_err(|_| {
-                actix_web::error::ErrorBadRequest("Invalid syndrome format".to_string())
+            s.parse::<usize>()
+                .map_err(|_| actix_web::error::ErrorBadRequest(format!("Invalid {} format", name)))
+        })
+        .collect()
+}
+
+/// parse a solver config given as a JSON string in a query
+fn parse_solver_config(config: Option<&str>) -> Result<Option<serde_json::Value>> {
+    config
+        .map(|config| {
+            serde_json::from_str(config).map_err(|error| {
+                actix_web::error::ErrorBadRequest(format!("invalid solver config: {}", error))
             })
         })
-        .collect::<Result<Vec<usize>, actix_web::Error>>()?;
+        .transpose()
+}
+
+pub async fn decode_common(query: &DecodeRequest) -> Result<DecodeResult> {
+    let code = find_code(&query.code_id).ok_or(actix_web::error::ErrorBadRequest(
+        "Code not found".to_string(),
+    ))?;
+    let syndrome = query.syndrome.clone();
     for vertex_index in syndrome.iter() {
         if *vertex_index >= code.visualize_positions.len() {
             return Err(actix_web::error::ErrorBadRequest(
@@ -65,26 +195,105 @@ pub async fn decode_common(query: &DecodeParams) -> Result<DecodeResult> {
     }
     // construct decoder
     let mut visualizer = None;
-    if query.with_json.is_some() || query.with_html.is_some() {
+    if query.with_json || query.with_html {
         visualizer = Some(
             Visualizer::new(Some(String::new()), code.visualize_positions.clone(), true).unwrap(),
         );
     }
-    let mut solver = SolverSerialJointSingleHair::new(
-        &Arc::new(code.solver_initializer.clone()),
-        json!({"cluster_node_limit": query.cluster_node_limit}),
-    );
-    let syndrome_pattern = SyndromePattern::new_vertices(syndrome.clone());
-    solver.solve_visualizer(syndrome_pattern.clone(), visualizer.as_mut());
-    let (subgraph, weight_range) = solver.subgraph_range_visualizer(visualizer.as_mut());
+    let erasures = code
+        .erased_edges(&query.erasures, &query.erased_edges)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let mut initializer = code
+        .reweighted_initializer(&query.overrides)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    erase_edges(&mut initializer, &erasures);
+    let measurement_llrs = measurement_llrs(
+        query.measurement_confidences.as_deref(),
+        query.measurement_llrs.as_deref(),
+    )
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    let measurement_stabilizers = match &measurement_llrs {
+        Some(llrs) => code
+            .add_measurement_errors(&mut initializer, llrs)
+            .map_err(actix_web::error::ErrorBadRequest)?,
+        None => vec![],
+    };
+    let solver_options = SolverOptions::new(
+        query.solver,
+        query.cluster_node_limit,
+        query.solver_config.as_ref(),
+    )
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    let syndrome_pattern = SyndromePattern::new(syndrome.clone(), erasures.clone());
+    let (subgraph, weight_range) = if query.preprocess {
+        let graph =
+            if query.overrides.is_empty() && erasures.is_empty() && measurement_llrs.is_none() {
+                cached_preprocess(&code)
+            } else {
+                Arc::new(preprocess(&initializer))
+            };
+        if visualizer.is_some() {
+            // the visualizer shows the whole simplified graph, so it is solved at once
+            let mut solver = solver_options.build(&graph.solver_initializer);
+            let syndrome_pattern = SyndromePattern::new(
+                syndrome.clone(),
+                (0..graph.edge_origins.len())
+                    .filter(|edge_index| erasures.contains(&graph.edge_origins[*edge_index]))
+                    .collect(),
+            );
+            solver.solve_visualizer(syndrome_pattern, visualizer.as_mut());
+            let (subgraph, weight_range) = solver.subgraph_range_visualizer(visualizer.as_mut());
+            let mut subgraph: Vec<usize> = subgraph
+                .iter()
+                .map(|edge_index| graph.edge_origins[*edge_index])
+                .collect();
+            subgraph.sort();
+            (subgraph, weight_range)
+        } else {
+            graph.decode(&syndrome, &solver_options)
+        }
+    } else {
+        let mut solver = solver_options.build(&Arc::new(initializer));
+        solver.solve_visualizer(syndrome_pattern, visualizer.as_mut());
+        let (mut subgraph, weight_range) = solver.subgraph_range_visualizer(visualizer.as_mut());
+        subgraph.sort();
+        (subgraph, weight_range)
+    };
 
+    // the hyperedges after those of the code are the measurement errors
+    let edge_num = code.solver_initializer.weighted_edges.len();
+    let (measurement_edges, subgraph): (Vec<usize>, Vec<usize>) = subgraph
+        .into_iter()
+        .partition(|edge_index| *edge_index >= edge_num);
+    let measurement_errors: Vec<usize> = measurement_edges
+        .iter()
+        .map(|edge_index| measurement_stabilizers[edge_index - edge_num])
+        .collect();
     let correction: Vec<(usize, String)> = subgraph
         .iter()
         .map(|edge_index| code.edge_errors[*edge_index].clone())
         .collect();
+    let equivalent_errors: Vec<Vec<(usize, String)>> = subgraph
+        .iter()
+        .map(|edge_index| code.edge_equivalent_errors[*edge_index].clone())
+        .collect();
+    let correction_events: Vec<Vec<(usize, String)>> = subgraph
+        .iter()
+        .map(|edge_index| code.edge_events[*edge_index].clone())
+        .collect();
+    let correlated_errors: Vec<Vec<usize>> = subgraph
+        .iter()
+        .map(|edge_index| code.edge_correlated_errors[*edge_index].clone())
+        .collect();
 
     Ok(DecodeResult {
         correction,
+        correction_edges: subgraph,
+        equivalent_errors,
+        correction_events,
+        correlated_errors,
+        erasures,
+        measurement_errors,
         weight_range,
         visualizer,
     })
@@ -98,36 +307,88 @@ pub async fn decode(req: HttpRequest, query: web::Query<DecodeParams>) -> Result
         .realip_remote_addr()
         .map(|ip| ip.to_string());
     log::error!(
-        "Decode request from {:?}: code_id={}, syndrome={}, with_json={}, with_html={}, cluster_node_limit={}",
+        "Decode request from {:?}: code_id={}, syndrome={}, with_json={}, with_html={}, cluster_node_limit={}, solver={:?}, solver_config={:?}, preprocess={}, erasures={:?}, erased_edges={:?}, error_probabilities={:?}, edge_weights={:?}, soft_information={}",
         remote_ip,
         query.code_id,
         query.syndrome,
         query.with_json.is_some(),
         query.with_html.is_some(),
         query.cluster_node_limit,
+        query.solver,
+        query.solver_config,
+        query.preprocess.is_some(),
+        query.erasures,
+        query.erased_edges,
+        query.error_probabilities,
+        query.edge_weights,
+        query.measurement_confidences.is_some() || query.measurement_llrs.is_some(),
     );
 
     // return Err(actix_web::error::ErrorBadRequest("Debug".to_string())); // debug
     // smol::Timer::after(std::time::Duration::from_secs(3)).await; // debug
 
-    let query = query.into_inner();
-    let mut decoded = decode_common(&query).await?;
+    let request = query.to_request()?;
+    decode_response(&request, remote_ip).await
+}
+
+#[post("/api/decode")]
+pub async fn decode_json(
+    req: HttpRequest,
+    body: web::Json<DecodeRequest>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::error!(
+        "Decode JSON request from {:?}: code_id={}, syndrome={:?}, with_json={}, with_html={}, cluster_node_limit={}, solver={:?}, solver_config={:?}, preprocess={}, erasures={:?}, erased_edges={:?}, overrides={:?}, soft_information={}",
+        remote_ip,
+        body.code_id,
+        body.syndrome,
+        body.with_json,
+        body.with_html,
+        body.cluster_node_limit,
+        body.solver,
+        body.solver_config,
+        body.preprocess,
+        body.erasures,
+        body.erased_edges,
+        body.overrides,
+        body.measurement_confidences.is_some() || body.measurement_llrs.is_some(),
+    );
+
+    decode_response(&body, remote_ip).await
+}
+
+/// decode and build the response shared by the GET and POST decoding endpoints
+async fn decode_response(
+    request: &DecodeRequest,
+    remote_ip: Option<String>,
+) -> Result<web::Json<serde_json::Map<String, serde_json::Value>>> {
+    let mut decoded = decode_common(request).await?;
 
     let mut result: serde_json::Map<String, serde_json::Value> = json!({
         "correction": decoded.correction,
+        "correction_edges": decoded.correction_edges,
+        "equivalent_errors": decoded.equivalent_errors,
+        "correction_events": decoded.correction_events,
+        "correlated_errors": decoded.correlated_errors,
+        "erasures": decoded.erasures,
+        "measurement_errors": decoded.measurement_errors,
         "lower": decoded.weight_range.lower.to_f64(),
         "upper": decoded.weight_range.upper.to_f64(),
     })
     .as_object()
     .unwrap()
     .clone();
-    if query.with_json.is_some() {
+    if request.with_json {
         result.insert(
             "json".to_string(),
             decoded.visualizer.as_mut().unwrap().get_visualizer_data(),
         );
     }
-    if query.with_html.is_some() {
+    if request.with_html {
         result.insert(
             "html".to_string(),
             decoded
@@ -141,11 +402,12 @@ pub async fn decode(req: HttpRequest, query: web::Query<DecodeParams>) -> Result
 
     if decoded.weight_range.lower + Rational::one() <= decoded.weight_range.upper {
         log::info!(
-            "Suboptimal decoding result from {:?}: code_id={}, syndrome={}, cluster_node_limit={}",
+            "Suboptimal decoding result from {:?}: code_id={}, syndrome={:?}, cluster_node_limit={}, solver={:?}",
             remote_ip,
-            query.code_id,
-            query.syndrome,
-            query.cluster_node_limit,
+            request.code_id,
+            request.syndrome,
+            request.cluster_node_limit,
+            request.solver,
         );
     }
 
@@ -173,9 +435,9 @@ pub async fn decoding_process(
     // return Err(actix_web::error::ErrorBadRequest("Debug".to_string())); // debug
     // smol::Timer::after(std::time::Duration::from_secs(3)).await; // debug
 
-    let mut query = query.into_inner();
-    query.with_html = Some("".to_string());
-    let mut decoded = decode_common(&query).await?;
+    let mut request = query.to_request()?;
+    request.with_html = true;
+    let mut decoded = decode_common(&request).await?;
     let html = decoded
         .visualizer
         .as_mut()
@@ -185,8 +447,166 @@ pub async fn decoding_process(
     Ok(web::Html::new(html))
 }
 
+/// cap the decoding work of a single batch request
+const MAX_BATCH_SHOTS: usize = 1_000_000;
+/// the decoding threads shared by all the batch requests, also bounded by the available parallelism
+const MAX_BATCH_WORKERS: usize = 16;
+/// a batch request takes one decoding thread per this many shots, as far as they are free
+const BATCH_WORKER_SHOTS: usize = 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct BatchDecodeParams {
+    /// the id of the code type
+    pub code_id: String,
+    /// the format of the uploaded shots and of the results
+    #[serde(default = "default_shot_format")]
+    pub format: ShotFormat,
+    /// the maximum number of nodes per cluster
+    #[serde(default = "default_cluster_node_limit")]
+    pub cluster_node_limit: usize,
+    /// the solver, see [`DecodeParams`]
+    #[serde(default)]
+    pub solver: SolverType,
+    /// the JSON config of the solver
+    pub solver_config: Option<String>,
+    /// if specified, the decoding hypergraph is simplified once before solving all the shots
+    pub preprocess: Option<String>,
+    /// the error probabilities for all the shots, in the format of "4:X:0.01,5:Z:0.02"
+    pub error_probabilities: Option<String>,
+    /// the hyperedge weights for all the shots, in the format of "3:0.5,7:2.1"
+    pub edge_weights: Option<String>,
+}
+
+fn default_shot_format() -> ShotFormat {
+    ShotFormat::Json
+}
+
+/// the response body of a batch decoding, with the results sent from the decoding thread as they are ready
+struct ShotStream {
+    receiver: tokio::sync::mpsc::Receiver<Vec<u8>>,
+}
+
+impl MessageBody for ShotStream {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<web::Bytes, Self::Error>>> {
+        self.receiver
+            .poll_recv(cx)
+            .map(|output| output.map(|output| Ok(web::Bytes::from(output))))
+    }
+}
+
+#[post("/api/decode/batch")]
+pub async fn decode_batch(
+    req: HttpRequest,
+    query: web::Query<BatchDecodeParams>,
+    body: web::Bytes,
+) -> Result<HttpResponse> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Batch decode request from {:?}: code_id={}, format={:?}, length={}, cluster_node_limit={}, solver={:?}, solver_config={:?}, preprocess={}, error_probabilities={:?}, edge_weights={:?}",
+        remote_ip,
+        query.code_id,
+        query.format,
+        body.len(),
+        query.cluster_node_limit,
+        query.solver,
+        query.solver_config,
+        query.preprocess.is_some(),
+        query.error_probabilities,
+        query.edge_weights,
+    );
+
+    let code = find_code(&query.code_id).ok_or(actix_web::error::ErrorBadRequest(
+        "Code not found".to_string(),
+    ))?;
+    let shots = query
+        .format
+        .parse_shots(&body, code.solver_initializer.vertex_num)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    if shots.len() > MAX_BATCH_SHOTS {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "{} shots exceed the limit of {}",
+            shots.len(),
+            MAX_BATCH_SHOTS
+        )));
+    }
+    let overrides = WeightOverrides::parse(
+        query.error_probabilities.as_deref(),
+        query.edge_weights.as_deref(),
+    )
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    let solver_options = SolverOptions::new(
+        query.solver,
+        query.cluster_node_limit,
+        parse_solver_config(query.solver_config.as_deref())?.as_ref(),
+    )
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    let decoder = BatchDecoder::new(code, &overrides, solver_options, query.preprocess.is_some())
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    // the requests share the decoding threads, taking as many of the free ones as the shots can use
+    let permit = BATCH_WORKERS
+        .try_acquire(shots.len().div_ceil(BATCH_WORKER_SHOTS).max(1))
+        .ok_or(actix_web::error::ErrorServiceUnavailable(
+            "All the batch decoding threads are busy, retry later",
+        ))?;
+    let worker_num = permit.count();
+    let format = query.format;
+    // the decoding stops once the client disconnects and the receiver is dropped
+    let (sender, receiver) = tokio::sync::mpsc::channel(worker_num);
+    tokio::task::spawn_blocking(move || {
+        decoder.decode_stream(&shots, format, worker_num, |output| {
+            sender.blocking_send(output).is_ok()
+        });
+        drop(permit);
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(ShotStream { receiver }))
+}
+
+#[get("/api/solvers")]
+pub async fn get_solvers(req: HttpRequest) -> impl Responder {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!("Solvers request from {:?}", remote_ip);
+
+    let solvers: Vec<serde_json::Value> = SolverType::ALL
+        .iter()
+        .map(|solver| json!({"solver": solver, "options": solver.allowed_options()}))
+        .collect();
+    web::Json(json!({
+        "solvers": solvers,
+        "default": SolverType::default(),
+        "max_cluster_node_limit": MAX_CLUSTER_NODE_LIMIT,
+        "max_timeout": MAX_SOLVER_TIMEOUT,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CodesParams {
+    /// the ids of uploaded codes to be listed after the built-in codes, in the format of "id1,id2";
+    /// uploaded codes are scoped to the session that knows their ids, so they are never listed otherwise
+    pub include: Option<String>,
+}
+
 #[get("/api/codes")]
-pub async fn get_codes(req: HttpRequest) -> impl Responder {
+pub async fn get_codes(req: HttpRequest, query: web::Query<CodesParams>) -> impl Responder {
     // log user request
     let remote_ip = req
         .connection_info()
@@ -194,10 +614,583 @@ pub async fn get_codes(req: HttpRequest) -> impl Responder {
         .map(|ip| ip.to_string());
     log::info!("Codes request from {:?}", remote_ip,);
 
-    let codes: Vec<ClientCodeInfo> = CODES.iter().map(|code| code.client_info.clone()).collect();
+    let mut codes: Vec<ClientCodeInfo> =
+        CODES.iter().map(|code| code.client_info.clone()).collect();
+    if let Some(include) = query.include.as_ref() {
+        for code_id in include.split(',').filter(|code_id| !code_id.is_empty()) {
+            if let Some(code) = UPLOADED_CODES.get(code_id) {
+                codes.push(code.client_info.clone());
+            }
+        }
+    }
     serde_json::to_string(&codes).unwrap()
 }
 
+#[post("/api/codes/custom")]
+pub async fn upload_custom(
+    req: HttpRequest,
+    body: web::Json<ClientCodeInfo>,
+) -> Result<impl Responder> {
+    let mut client_info = body.into_inner();
+    // the id is always generated by the server to avoid collision with other codes
+    client_info.id = String::new();
+    let code_id = content_code_id("custom", &client_info);
+    client_info.id = code_id.clone();
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Custom code upload from {:?}: code_id={}",
+        remote_ip,
+        code_id
+    );
+
+    // laying out and validating the code scale with its size, so they run off the async worker
+    let code = web::block(move || {
+        // codes without geometry are laid out automatically
+        if client_info.data_qubit_positions.is_empty() && !client_info.data_qubit_actions.is_empty()
+        {
+            client_info.apply_auto_layout();
+        }
+        client_info.check_consistency()?;
+        client_info.fill_logical_observables();
+        register_code(ServerCodeInfo::from_client_info(client_info))
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[post("/api/codes/parity-check")]
+pub async fn upload_parity_check(
+    req: HttpRequest,
+    body: web::Json<ParityCheckCode>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    let code_id = body.code_id();
+    log::info!(
+        "Parity-check upload from {:?}: code_id={}",
+        remote_ip,
+        code_id
+    );
+
+    // laying out and validating the code scale with its size, so they run off the async worker
+    let code = web::block(move || register_code(body.build(&code_id)?))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[post("/api/codes/stabilizers")]
+pub async fn upload_stabilizers(
+    req: HttpRequest,
+    body: web::Json<StabilizerCode>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    let code_id = body.code_id();
+    log::info!(
+        "Stabilizer upload from {:?}: code_id={}",
+        remote_ip,
+        code_id
+    );
+
+    // laying out and validating the code scale with its size, so they run off the async worker
+    let code = web::block(move || register_code(body.build(&code_id)?))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemParams {
+    /// the display name of the code
+    pub name: Option<String>,
+}
+
+#[post("/api/codes/dem")]
+pub async fn upload_dem(
+    req: HttpRequest,
+    query: web::Query<DemParams>,
+    body: String,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    let code_id = content_code_id("dem", &body);
+    log::info!(
+        "Detector error model upload from {:?}: code_id={}, length={}",
+        remote_ip,
+        code_id,
+        body.len()
+    );
+
+    // unrolling the repeat blocks and validating the graph take a while, so they run off the async worker
+    let name = query.into_inner().name;
+    let code = web::block(move || {
+        let model = DetectorErrorModel::parse(&body)?;
+        let name = name.unwrap_or_else(|| {
+            format!(
+                "Detector Error Model ({} detectors, {} errors)",
+                model.detector_num,
+                model.errors.len()
+            )
+        });
+        register_code(model.build(&code_id, &name))
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StimParams {
+    /// the physical error rate of the circuit-level noise
+    #[serde(default = "default_stim_p")]
+    pub p: f64,
+    /// the number of measurement rounds; if not specified, the code distance is used
+    pub rounds: Option<usize>,
+    /// the basis of the memory experiment, "Z" or "X"
+    #[serde(default = "default_stim_basis")]
+    pub basis: String,
+}
+
+fn default_stim_p() -> f64 {
+    0.001
+}
+
+fn default_stim_basis() -> String {
+    "Z".to_string()
+}
+
+#[get("/api/codes/{code_id}/stim")]
+pub async fn get_stim_circuit(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<StimParams>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Stim circuit request from {:?}: code_id={}, p={}, rounds={:?}, basis={}",
+        remote_ip,
+        path,
+        query.p,
+        query.rounds,
+        query.basis,
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    let rounds = query.rounds.unwrap_or(code.client_info.d.max(1));
+    if rounds > MAX_STIM_ROUNDS {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "at most {} rounds are supported",
+            MAX_STIM_ROUNDS
+        )));
+    }
+    let circuit = code
+        .client_info
+        .stim_memory_circuit(query.p, rounds, &query.basis)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(circuit)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphParams {
+    /// one of "dem", "json", "dot" and "graphml"
+    #[serde(default = "default_graph_format")]
+    pub format: GraphFormat,
+}
+
+fn default_graph_format() -> GraphFormat {
+    GraphFormat::Json
+}
+
+#[get("/api/codes/{code_id}/graph")]
+pub async fn get_graph(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<GraphParams>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Graph request from {:?}: code_id={}, format={:?}",
+        remote_ip,
+        path,
+        query.format,
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    Ok(HttpResponse::Ok()
+        .content_type(query.format.content_type())
+        .body(code.export_graph(query.format)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatrixParams {
+    /// the type of the stabilizers, "X" or "Z"
+    #[serde(rename = "type")]
+    pub check_type: String,
+    /// one of "alist", "mtx" and "json"
+    #[serde(default = "default_matrix_format")]
+    pub format: String,
+}
+
+fn default_matrix_format() -> String {
+    "alist".to_string()
+}
+
+#[get("/api/codes/{code_id}/matrix")]
+pub async fn get_matrix(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<MatrixParams>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Matrix request from {:?}: code_id={}, type={}, format={}",
+        remote_ip,
+        path,
+        query.check_type,
+        query.format,
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    let check_type = query.check_type.to_uppercase();
+    if check_type != "X" && check_type != "Z" {
+        return Err(actix_web::error::ErrorBadRequest(
+            "The type should be X or Z".to_string(),
+        ));
+    }
+    let matrix = code
+        .client_info
+        .parity_check_matrix(&check_type)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let (content_type, body) = match query.format.as_str() {
+        "alist" => ("text/plain", matrix.to_alist()),
+        "mtx" => ("text/plain", matrix.to_matrix_market()),
+        "json" => (
+            "application/json",
+            Ok(serde_json::to_string(&matrix).unwrap()),
+        ),
+        _ => {
+            return Err(actix_web::error::ErrorBadRequest(
+                "The format should be alist, mtx or json".to_string(),
+            ));
+        }
+    };
+    let body = body.map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().content_type(content_type).body(body))
+}
+
+/// the maximum number of error sets explored by a distance request
+const MAX_DISTANCE_STATES: usize = 1_000_000;
+/// the distance searches running at the same time, each taking up to hundreds of MB
+static DISTANCE_SEARCHES: ConcurrencyLimit = ConcurrencyLimit::new(2);
+
+#[derive(Debug, Deserialize)]
+pub struct DistanceParams {
+    /// the number of error sets to explore before giving bounds instead of the exact distance
+    #[serde(default = "default_max_states")]
+    pub max_states: usize,
+}
+
+fn default_max_states() -> usize {
+    DEFAULT_MAX_STATES
+}
+
+#[get("/api/codes/{code_id}/distance")]
+pub async fn get_distance(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DistanceParams>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Distance request from {:?}: code_id={}, max_states={}",
+        remote_ip,
+        path,
+        query.max_states,
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    if query.max_states > MAX_DISTANCE_STATES {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "At most {} states can be explored",
+            MAX_DISTANCE_STATES
+        )));
+    }
+    let max_states = query.max_states;
+    let permit =
+        DISTANCE_SEARCHES
+            .try_acquire(1)
+            .ok_or(actix_web::error::ErrorServiceUnavailable(
+                "Too many distance searches, retry later",
+            ))?;
+    // the search may take seconds, so it does not block the worker
+    let distance = web::block(move || {
+        let _permit = permit;
+        code.client_info.code_distance(max_states)
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(distance))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetadataParams {
+    /// the number of error sets to explore for the distance
+    #[serde(default = "default_metadata_max_states")]
+    pub max_states: usize,
+}
+
+fn default_metadata_max_states() -> usize {
+    100_000
+}
+
+#[get("/api/codes/{code_id}/metadata")]
+pub async fn get_metadata(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<MetadataParams>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Metadata request from {:?}: code_id={}, max_states={}",
+        remote_ip,
+        path,
+        query.max_states,
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    if query.max_states > MAX_DISTANCE_STATES {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "At most {} states can be explored",
+            MAX_DISTANCE_STATES
+        )));
+    }
+    let max_states = query.max_states;
+    let permit =
+        DISTANCE_SEARCHES
+            .try_acquire(1)
+            .ok_or(actix_web::error::ErrorServiceUnavailable(
+                "Too many distance searches, retry later",
+            ))?;
+    let metadata = web::block(move || {
+        let _permit = permit;
+        code.metadata(max_states)
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(web::Json(metadata))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalibrationParams {
+    /// the format of the calibration, "json" or "csv"
+    #[serde(default = "default_calibration_format")]
+    pub format: String,
+}
+
+fn default_calibration_format() -> String {
+    "json".to_string()
+}
+
+#[post("/api/codes/{code_id}/calibration")]
+pub async fn upload_calibration(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<CalibrationParams>,
+    body: String,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Calibration upload from {:?}: code_id={}, format={}, length={}",
+        remote_ip,
+        path,
+        query.format,
+        body.len()
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    // building and validating the calibrated code scale with the code, so they run off the async worker
+    let format = query.into_inner().format;
+    let code = web::block(move || {
+        let calibration = match format.as_str() {
+            "json" => DeviceCalibration::parse_json(&body),
+            "csv" => DeviceCalibration::parse_csv(&body),
+            _ => Err(format!("Unknown calibration format {}", format)),
+        }?;
+        let code_id = content_code_id(&format!("{}-calibrated", path), &calibration);
+        register_code(code.calibrated(&calibration, &code_id)?)
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[post("/api/codes/{code_id}/correlated")]
+pub async fn upload_correlated_errors(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<Vec<CorrelatedError>>,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Correlated errors upload from {:?}: code_id={}, correlated_errors={}",
+        remote_ip,
+        path,
+        body.len()
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    // merging the errors and validating the code scale with its size, so they run off the async worker
+    let code = web::block(move || {
+        let code_id = content_code_id(&format!("{}-correlated", path), &body.0);
+        register_code(code.with_correlated_errors(&body, &code_id)?)
+    })
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(code.client_info.clone()))
+}
+
+#[post("/api/codes/{code_id}/learn")]
+pub async fn learn_noise(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+) -> Result<impl Responder> {
+    // log user request
+    let remote_ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    log::info!(
+        "Noise learning request from {:?}: code_id={}, length={}",
+        remote_ip,
+        path,
+        body.len()
+    );
+
+    let code = find_code(&path).ok_or(actix_web::error::ErrorNotFound(
+        "Code not found".to_string(),
+    ))?;
+    let syndromes = parse_syndromes(&body, code.solver_initializer.vertex_num)
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let model = web::block(move || code.learn_noise_model(&syndromes))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    Ok(web::Json(model))
+}
+
+/// validate an uploaded code and register it
+pub fn register_code(code: ServerCodeInfo) -> Result<Arc<ServerCodeInfo>, String> {
+    code.validate()
+        .map_err(|error| format!("Invalid code: {}", error))?;
+    UPLOADED_CODES.insert(code)
+}
+
+/// look up a code among the built-in codes and the uploaded codes
+pub fn find_code(code_id: &str) -> Option<Arc<ServerCodeInfo>> {
+    CODES_MAP
+        .get(code_id)
+        .cloned()
+        .or_else(|| UPLOADED_CODES.get(code_id))
+        .or_else(|| GENERATED_CODES.get(code_id))
+        .or_else(|| {
+            // surface codes of other noise models are generated on demand from their id; they are kept apart from
+            // the uploaded codes, so that they neither evict the uploads nor get persisted
+            let code = RotatedSurfaceCode::from_code_id(code_id, MAX_GENERATED_D)?;
+            let code = Arc::new(ServerCodeInfo::from(&code));
+            GENERATED_CODES.insert(code_id.to_string(), code.clone());
+            Some(code)
+        })
+}
+
+/// the maximum number of generated codes kept, keyed by the requested id as it may not be canonical
+const MAX_GENERATED_CODES: usize = 32;
+
+/// the maximum number of codes whose preprocessed graphs are kept
+const MAX_PREPROCESSED_GRAPHS: usize = 64;
+
+/// the preprocessed graph of a code without any override, which is the same for all its requests;
+/// the code is kept alongside, so that a code replaced under the same id is preprocessed again
+fn cached_preprocess(code: &Arc<ServerCodeInfo>) -> Arc<PreprocessedGraph> {
+    let code_id = &code.client_info.id;
+    if let Some((_, graph)) = PREPROCESSED_GRAPHS
+        .get(code_id)
+        .filter(|(cached_code, _)| Arc::ptr_eq(cached_code, code))
+    {
+        return graph;
+    }
+    let graph = Arc::new(preprocess(&code.solver_initializer));
+    PREPROCESSED_GRAPHS.insert(code_id.clone(), (code.clone(), graph.clone()));
+    graph
+}
+
+/// the maximum distance of the surface codes generated from their id
+const MAX_GENERATED_D: usize = 25;
+
 lazy_static! {
     static ref CODES: Vec<ServerCodeInfo> = {
         vec![
@@ -211,15 +1204,31 @@ lazy_static! {
             ServerCodeInfo::from(&TriangularColorCodeBitFlip::new(5)),
         ]
     };
-    static ref CODES_MAP: HashMap<String, ServerCodeInfo> = {
+    static ref CODES_MAP: HashMap<String, Arc<ServerCodeInfo>> = {
         let mut map = HashMap::new();
         for code in CODES.iter() {
-            map.insert(code.client_info.id.clone(), code.clone());
+            map.insert(code.client_info.id.clone(), Arc::new(code.clone()));
         }
         map
     };
+    static ref UPLOADED_CODES: CodeRegistry = CodeRegistry::new();
+    static ref BATCH_WORKERS: ConcurrencyLimit = ConcurrencyLimit::new(
+        std::thread::available_parallelism()
+            .map_or(1, |parallelism| parallelism.get())
+            .min(MAX_BATCH_WORKERS)
+    );
+    static ref GENERATED_CODES: BoundedCache<Arc<ServerCodeInfo>> =
+        BoundedCache::new(MAX_GENERATED_CODES);
+    static ref PREPROCESSED_GRAPHS: BoundedCache<(Arc<ServerCodeInfo>, Arc<PreprocessedGraph>)> =
+        BoundedCache::new(MAX_PREPROCESSED_GRAPHS);
 }
 
+/// the maximum number of rounds in the generated Stim circuits
+const MAX_STIM_ROUNDS: usize = 1000;
+
+/// the maximum size of an uploaded JSON body in bytes
+const MAX_UPLOAD_SIZE: usize = 4 * 1024 * 1024;
+
 #[get("/")]
 pub async fn index() -> impl Responder {
     "MWPF Backend is running! Use /decode with query parameters."
@@ -234,6 +1243,58 @@ struct Args {
     /// Port to bind to
     #[arg(short, long, default_value = "8080")]
     port: u16,
+    /// Directory to persist the uploaded codes; if not specified, they are kept in memory only
+    #[arg(long)]
+    store_dir: Option<std::path::PathBuf>,
+    /// Seconds an uploaded code is kept since its last use
+    #[arg(long, default_value = "86400")]
+    code_ttl: u64,
+    /// Maximum total size of the uploaded codes in bytes
+    #[arg(long, default_value = "268435456")]
+    store_quota: usize,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Learn the noise model of a code from syndromes instead of starting the server
+    Learn {
+        /// The id of a built-in, generated or stored code
+        #[arg(long)]
+        code_id: String,
+        /// File of syndromes, one shot per line as "1,2,3"
+        #[arg(long)]
+        syndromes: std::path::PathBuf,
+        /// File to write the learned noise model to; if not specified, it is printed
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+/// learn a noise model from a file of syndromes and write it as JSON
+fn learn_command(
+    code_id: &str,
+    syndromes: &std::path::Path,
+    output: Option<&std::path::Path>,
+) -> std::io::Result<()> {
+    let code = find_code(code_id)
+        .ok_or_else(|| std::io::Error::other(format!("Code {} not found", code_id)))?;
+    let content = std::fs::read_to_string(syndromes)?;
+    let model = parse_syndromes(&content, code.solver_initializer.vertex_num)
+        .and_then(|syndromes| code.learn_noise_model(&syndromes))
+        .map_err(std::io::Error::other)?;
+    let json = serde_json::to_string_pretty(&model).map_err(std::io::Error::other)?;
+    match output {
+        Some(output) => std::fs::write(output, json)?,
+        None => println!("{}", json),
+    }
+    log::info!(
+        "Learned the noise model of {} from {} shots",
+        code_id,
+        model.shots
+    );
+    Ok(())
 }
 
 #[actix_web::main]
@@ -242,11 +1303,11 @@ pub async fn main() -> std::io::Result<()> {
 
     let args = Args::parse();
 
-    log::info!(
-        "Starting MWPF Backend server on {}:{}...",
-        args.ip,
-        args.port
-    );
+    for code in CODES.iter() {
+        if let Err(error) = code.validate() {
+            log::error!("Invalid built-in code {}: {}", code.client_info.id, error);
+        }
+    }
 
     log::info!(
         "Available codes: {:?}",
@@ -256,12 +1317,53 @@ pub async fn main() -> std::io::Result<()> {
             .collect::<Vec<_>>()
     );
 
+    let loaded = UPLOADED_CODES.configure(RegistryConfig {
+        ttl: std::time::Duration::from_secs(args.code_ttl),
+        quota: args.store_quota,
+        store_dir: args.store_dir.clone(),
+    })?;
+    if loaded > 0 {
+        log::info!("Loaded {} uploaded codes from {:?}", loaded, args.store_dir);
+    }
+
+    if let Some(Command::Learn {
+        code_id,
+        syndromes,
+        output,
+    }) = &args.command
+    {
+        return learn_command(code_id, syndromes, output.as_deref());
+    }
+
+    log::info!(
+        "Starting MWPF Backend server on {}:{}...",
+        args.ip,
+        args.port
+    );
+
     HttpServer::new(|| {
         App::new()
+            .app_data(web::JsonConfig::default().limit(MAX_UPLOAD_SIZE))
+            .app_data(web::PayloadConfig::new(MAX_UPLOAD_SIZE))
             .service(index)
             .service(decode)
+            .service(decode_json)
+            .service(decode_batch)
             .service(get_codes)
+            .service(get_solvers)
             .service(decoding_process)
+            .service(upload_parity_check)
+            .service(upload_stabilizers)
+            .service(upload_custom)
+            .service(upload_dem)
+            .service(get_stim_circuit)
+            .service(get_graph)
+            .service(get_matrix)
+            .service(get_distance)
+            .service(get_metadata)
+            .service(upload_calibration)
+            .service(upload_correlated_errors)
+            .service(learn_noise)
     })
     .bind((args.ip, args.port))?
     .run()
@@ -0,0 +1,157 @@
+use crate::codes::*;
+use crate::distance::CodeDistance;
+use crate::symplectic::stabilizer_basis;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// do not compute k of codes larger than this, as the elimination is O(n^3)
+const MAX_RANK_DATA_QUBITS: usize = 10_000;
+
+/// the figures of a code as an [[n, k, d]] code and of its decoding hypergraph
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeMetadata {
+    pub id: String,
+    pub name: String,
+    pub n: usize,
+    /// `None` if the code is too large or its stabilizers do not commute
+    pub k: Option<usize>,
+    /// the distance claimed by the code, 0 if unknown
+    pub d: usize,
+    /// the distance computed under the noise model, `None` if the code has no logical observable
+    pub distance: Option<CodeDistance>,
+    pub stabilizer_num: usize,
+    /// the number of stabilizers of each weight
+    pub check_weights: BTreeMap<usize, usize>,
+    /// the number of data qubits checked by each number of stabilizers
+    pub qubit_degrees: BTreeMap<usize, usize>,
+    pub is_css: bool,
+    /// the number of single-qubit errors in `data_qubit_actions`
+    pub error_mechanism_num: usize,
+    /// the number of errors that trigger no stabilizer and are left out of the decoding hypergraph
+    pub undetectable_errors: usize,
+    pub hyperedge_num: usize,
+    /// the maximum number of vertices of a hyperedge
+    pub max_hyperedge_degree: usize,
+    /// the number of errors merged into the hyperedge of another error with the same syndrome
+    pub duplicate_hyperedges: usize,
+    /// the number of errors acting on several data qubits at once
+    pub correlated_error_num: usize,
+}
+
+impl ClientCodeInfo {
+    /// whether every stabilizer is entirely of X type or entirely of Z type
+    pub fn is_css(&self) -> bool {
+        self.stabilizer_checks.iter().all(|check| {
+            check.iter().all(|(_, check_type)| check_type == "X")
+                || check.iter().all(|(_, check_type)| check_type == "Z")
+        })
+    }
+}
+
+impl ServerCodeInfo {
+    /// compute the metadata, searching at most `max_states` error sets for the distance
+    pub fn metadata(&self, max_states: usize) -> CodeMetadata {
+        let client_info = &self.client_info;
+        let n = client_info.data_qubit_actions.len();
+        let mut check_weights = BTreeMap::new();
+        let mut qubit_checks = vec![0; n];
+        for check in client_info.stabilizer_checks.iter() {
+            *check_weights.entry(check.len()).or_insert(0) += 1;
+            for (data_index, _) in check.iter() {
+                qubit_checks[*data_index] += 1;
+            }
+        }
+        let mut qubit_degrees = BTreeMap::new();
+        for degree in qubit_checks {
+            *qubit_degrees.entry(degree).or_insert(0) += 1;
+        }
+        let k = if n <= MAX_RANK_DATA_QUBITS {
+            stabilizer_basis(n, &client_info.stabilizer_checks)
+                .ok()
+                .map(|stabilizers| n - stabilizers.rank())
+        } else {
+            None
+        };
+        let distance = if client_info.logical_observables.is_empty() {
+            None
+        } else {
+            client_info.code_distance(max_states).ok()
+        };
+        let syndromes = client_info
+            .data_qubit_actions
+            .iter()
+            .flat_map(|actions| actions.values());
+        let error_mechanism_num = syndromes.clone().count();
+        let undetectable_errors = syndromes.filter(|syndrome| syndrome.is_empty()).count();
+        let hyperedge_num = self.solver_initializer.weighted_edges.len();
+        CodeMetadata {
+            id: client_info.id.clone(),
+            name: client_info.name.clone(),
+            n,
+            k,
+            d: client_info.d,
+            distance,
+            stabilizer_num: client_info.stabilizer_checks.len(),
+            check_weights,
+            qubit_degrees,
+            is_css: client_info.is_css(),
+            error_mechanism_num,
+            undetectable_errors,
+            hyperedge_num,
+            max_hyperedge_degree: self
+                .solver_initializer
+                .weighted_edges
+                .iter()
+                .map(|hyperedge| hyperedge.vertices.len())
+                .max()
+                .unwrap_or(0),
+            duplicate_hyperedges: self
+                .edge_equivalent_errors
+                .iter()
+                .zip(self.edge_correlated_errors.iter())
+                .map(|(errors, correlated_errors)| errors.len() + correlated_errors.len() - 1)
+                .sum(),
+            correlated_error_num: client_info.correlated_errors.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::DEFAULT_MAX_STATES;
+    use crate::stabilizer::*;
+
+    #[test]
+    fn test_code_metadata() {
+        // cargo test -- test_code_metadata --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        let metadata = code.metadata(DEFAULT_MAX_STATES);
+        println!("{}", serde_json::to_string(&metadata).unwrap());
+        assert_eq!((metadata.n, metadata.k, metadata.d), (9, Some(1), 3));
+        assert_eq!(metadata.distance.as_ref().unwrap().upper_bound, Some(3));
+        assert!(metadata.is_css);
+        assert_eq!(metadata.check_weights, BTreeMap::from([(2, 4), (4, 4)]));
+        assert_eq!(metadata.qubit_degrees.values().sum::<usize>(), 9);
+        assert_eq!(metadata.error_mechanism_num, 27);
+        assert_eq!(
+            metadata.hyperedge_num + metadata.duplicate_hyperedges + metadata.undetectable_errors,
+            27
+        );
+        // a non-CSS code
+        let code = StabilizerCode {
+            stabilizers: ["XZZXI", "IXZZX", "XIXZZ", "ZXIXZ"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+        .build("five")
+        .unwrap();
+        let metadata = code.metadata(DEFAULT_MAX_STATES);
+        assert_eq!((metadata.n, metadata.k), (5, Some(1)));
+        assert!(!metadata.is_css);
+        assert_eq!(metadata.max_hyperedge_degree, 4);
+        assert_eq!(metadata.duplicate_hyperedges, 0);
+    }
+}
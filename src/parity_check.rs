@@ -0,0 +1,395 @@
+use crate::codes::*;
+use crate::layout::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// the largest matrix accepted, which bounds the per-column and per-row allocations of a declared sparse size
+const MAX_MATRIX_COLUMNS: usize = 100_000;
+const MAX_MATRIX_ROWS: usize = 100_000;
+
+/// a binary matrix, either dense as rows of 0/1 or sparse as the column indices of the 1s in each row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BinaryMatrix {
+    Dense(Vec<Vec<u8>>),
+    Sparse {
+        columns: usize,
+        rows: Vec<Vec<usize>>,
+    },
+}
+
+impl BinaryMatrix {
+    pub fn columns(&self) -> usize {
+        match self {
+            BinaryMatrix::Dense(rows) => rows.first().map(|row| row.len()).unwrap_or(0),
+            BinaryMatrix::Sparse { columns, .. } => *columns,
+        }
+    }
+
+    /// the sorted column indices of the 1s in each row
+    pub fn sparse_rows(&self) -> Result<Vec<Vec<usize>>, String> {
+        let columns = self.columns();
+        let row_num = match self {
+            BinaryMatrix::Dense(rows) => rows.len(),
+            BinaryMatrix::Sparse { rows, .. } => rows.len(),
+        };
+        if columns > MAX_MATRIX_COLUMNS || row_num > MAX_MATRIX_ROWS {
+            return Err(format!(
+                "the matrix of {} rows and {} columns exceeds the limit of {} rows and {} columns",
+                row_num, columns, MAX_MATRIX_ROWS, MAX_MATRIX_COLUMNS
+            ));
+        }
+        let mut sparse_rows = vec![];
+        match self {
+            BinaryMatrix::Dense(rows) => {
+                for (row_idx, row) in rows.iter().enumerate() {
+                    if row.len() != columns {
+                        return Err(format!(
+                            "row {} has {} columns, expected {}",
+                            row_idx,
+                            row.len(),
+                            columns
+                        ));
+                    }
+                    let mut sparse_row = vec![];
+                    for (column, &value) in row.iter().enumerate() {
+                        match value {
+                            0 => {}
+                            1 => sparse_row.push(column),
+                            _ => {
+                                return Err(format!(
+                                    "entry ({}, {}) is {}, expected 0 or 1",
+                                    row_idx, column, value
+                                ));
+                            }
+                        }
+                    }
+                    sparse_rows.push(sparse_row);
+                }
+            }
+            BinaryMatrix::Sparse { rows, .. } => {
+                for (row_idx, row) in rows.iter().enumerate() {
+                    let mut sparse_row = row.clone();
+                    sparse_row.sort();
+                    for (idx, &column) in sparse_row.iter().enumerate() {
+                        if column >= columns {
+                            return Err(format!(
+                                "row {} has column index {} out of range {}",
+                                row_idx, column, columns
+                            ));
+                        }
+                        if idx > 0 && sparse_row[idx - 1] == column {
+                            return Err(format!(
+                                "row {} has duplicate column index {}",
+                                row_idx, column
+                            ));
+                        }
+                    }
+                    sparse_rows.push(sparse_row);
+                }
+            }
+        }
+        Ok(sparse_rows)
+    }
+}
+
+impl BinaryMatrix {
+    /// the column indices of the 1s in each row, which are row indices when the matrix is transposed
+    fn sparse_columns(&self) -> Result<Vec<Vec<usize>>, String> {
+        let sparse_rows = self.sparse_rows()?;
+        let mut sparse_columns = vec![vec![]; self.columns()];
+        for (row_idx, row) in sparse_rows.iter().enumerate() {
+            for &column in row.iter() {
+                sparse_columns[column].push(row_idx);
+            }
+        }
+        Ok(sparse_columns)
+    }
+
+    /// serialize in MacKay's alist format with 1-based indices, padding the lists with 0
+    pub fn to_alist(&self) -> Result<String, String> {
+        let rows = self.sparse_rows()?;
+        let columns = self.sparse_columns()?;
+        let max_weight =
+            |lists: &Vec<Vec<usize>>| lists.iter().map(|list| list.len()).max().unwrap_or(0);
+        let weights = |lists: &Vec<Vec<usize>>| {
+            lists
+                .iter()
+                .map(|list| list.len().to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let padded = |list: &Vec<usize>, length: usize| {
+            (0..length)
+                .map(|idx| {
+                    list.get(idx)
+                        .map(|index| index + 1)
+                        .unwrap_or(0)
+                        .to_string()
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let mut alist = String::new();
+        writeln!(alist, "{} {}", columns.len(), rows.len()).unwrap();
+        writeln!(alist, "{} {}", max_weight(&columns), max_weight(&rows)).unwrap();
+        writeln!(alist, "{}", weights(&columns)).unwrap();
+        writeln!(alist, "{}", weights(&rows)).unwrap();
+        for column in columns.iter() {
+            writeln!(alist, "{}", padded(column, max_weight(&columns))).unwrap();
+        }
+        for row in rows.iter() {
+            writeln!(alist, "{}", padded(row, max_weight(&rows))).unwrap();
+        }
+        Ok(alist)
+    }
+
+    /// serialize in the MatrixMarket coordinate format with 1-based indices
+    pub fn to_matrix_market(&self) -> Result<String, String> {
+        let rows = self.sparse_rows()?;
+        let mut mtx = String::new();
+        writeln!(mtx, "%%MatrixMarket matrix coordinate integer general").unwrap();
+        writeln!(
+            mtx,
+            "{} {} {}",
+            rows.len(),
+            self.columns(),
+            rows.iter().map(|row| row.len()).sum::<usize>()
+        )
+        .unwrap();
+        for (row_idx, row) in rows.iter().enumerate() {
+            for column in row.iter() {
+                writeln!(mtx, "{} {} 1", row_idx + 1, column + 1).unwrap();
+            }
+        }
+        Ok(mtx)
+    }
+}
+
+impl ClientCodeInfo {
+    /// the parity-check matrix of the stabilizers of the given type ("X" or "Z") of a CSS code;
+    /// fails if any stabilizer mixes different Pauli types
+    pub fn parity_check_matrix(&self, check_type: &str) -> Result<BinaryMatrix, String> {
+        let mut rows = vec![];
+        for (stabilizer_idx, check) in self.stabilizer_checks.iter().enumerate() {
+            let Some((_, first_type)) = check.first() else {
+                continue;
+            };
+            if check.iter().any(|(_, term_type)| term_type != first_type) || first_type == "Y" {
+                return Err(format!(
+                    "stabilizer {} is not of a single X or Z type, so the code is not CSS",
+                    stabilizer_idx
+                ));
+            }
+            if first_type == check_type {
+                let mut row: Vec<usize> = check.iter().map(|(data_index, _)| *data_index).collect();
+                row.sort();
+                rows.push(row);
+            }
+        }
+        Ok(BinaryMatrix::Sparse {
+            columns: self.data_qubit_positions.len(),
+            rows,
+        })
+    }
+}
+
+/// a code given by its parity-check matrices: either `h_x` and/or `h_z` of a CSS code,
+/// or a single classical `h` whose checks detect bit-flip errors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParityCheckCode {
+    pub name: Option<String>,
+    /// the code distance if known, otherwise 0
+    #[serde(default)]
+    pub d: usize,
+    pub h: Option<BinaryMatrix>,
+    pub h_x: Option<BinaryMatrix>,
+    pub h_z: Option<BinaryMatrix>,
+    #[serde(default)]
+    pub logical_observables: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+}
+
+impl ParityCheckCode {
+    /// the code id derived from the content of the matrices
+    pub fn code_id(&self) -> String {
+        crate::registry::content_code_id("pcm", self)
+    }
+
+    pub fn build_client_info(&self, id: &str) -> Result<ClientCodeInfo, String> {
+        // collect the checks of each type and the errors to be decoded
+        let (matrices, error_types): (Vec<(&BinaryMatrix, &str)>, Vec<&str>) =
+            match (&self.h, &self.h_x, &self.h_z) {
+                (Some(h), None, None) => (vec![(h, "Z")], vec!["X"]),
+                (None, Some(h_x), Some(h_z)) => (vec![(h_x, "X"), (h_z, "Z")], vec!["X", "Y", "Z"]),
+                (None, Some(h_x), None) => (vec![(h_x, "X")], vec!["Z"]),
+                (None, None, Some(h_z)) => (vec![(h_z, "Z")], vec!["X"]),
+                (None, None, None) => return Err("no parity-check matrix is given".to_string()),
+                _ => return Err("`h` cannot be used together with `h_x` or `h_z`".to_string()),
+            };
+        let data_qubit_num = matrices[0].0.columns();
+        if data_qubit_num == 0 {
+            return Err("the parity-check matrix has no column".to_string());
+        }
+        let mut stabilizer_checks: Vec<Vec<(usize, String)>> = vec![];
+        for (matrix, check_type) in matrices.iter() {
+            if matrix.columns() != data_qubit_num {
+                return Err(format!(
+                    "the parity-check matrices have different number of columns: {} and {}",
+                    data_qubit_num,
+                    matrix.columns()
+                ));
+            }
+            for row in matrix.sparse_rows()? {
+                stabilizer_checks.push(
+                    row.into_iter()
+                        .map(|data_index| (data_index, check_type.to_string()))
+                        .collect(),
+                );
+            }
+        }
+        for (observable_idx, observable) in self.logical_observables.iter().enumerate() {
+            for (data_index, check_type) in observable.iter() {
+                if *data_index >= data_qubit_num || !["X", "Y", "Z"].contains(&check_type.as_str())
+                {
+                    return Err(format!(
+                        "logical observable {} has invalid term ({}, {})",
+                        observable_idx, data_index, check_type
+                    ));
+                }
+            }
+        }
+        let layout = auto_layout(data_qubit_num, &stabilizer_checks);
+        let mut client_info = ClientCodeInfo {
+            id: id.to_string(),
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Parity-Check Code (n={})", data_qubit_num)),
+            d: self.d,
+            data_qubit_actions: data_qubit_actions_from_checks(
+                data_qubit_num,
+                &stabilizer_checks,
+                &error_types,
+            ),
+            data_qubit_positions: layout.data_qubit_positions,
+            stabilizer_positions: layout.stabilizer_positions,
+            stabilizer_shapes: layout.stabilizer_shapes,
+            stabilizer_colors: stabilizer_checks
+                .iter()
+                .map(|check| stabilizer_color(check))
+                .collect(),
+            stabilizer_checks,
+            logical_observables: self.logical_observables.clone(),
+            error_probabilities: vec![],
+            correlated_errors: vec![],
+            is_detector_error_model: false,
+            detector_coordinates: vec![],
+        };
+        client_info.fill_logical_observables();
+        Ok(client_info)
+    }
+
+    pub fn build(&self, id: &str) -> Result<ServerCodeInfo, String> {
+        Ok(ServerCodeInfo::from_client_info(
+            self.build_client_info(id)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parity_check_steane_code() {
+        // cargo test -- test_parity_check_steane_code --nocapture
+        let hamming = BinaryMatrix::Dense(vec![
+            vec![1, 0, 1, 0, 1, 0, 1],
+            vec![0, 1, 1, 0, 0, 1, 1],
+            vec![0, 0, 0, 1, 1, 1, 1],
+        ]);
+        let code = ParityCheckCode {
+            h_x: Some(hamming.clone()),
+            h_z: Some(BinaryMatrix::Sparse {
+                columns: 7,
+                rows: vec![vec![0, 2, 4, 6], vec![1, 2, 5, 6], vec![3, 4, 5, 6]],
+            }),
+            ..Default::default()
+        };
+        let server_info = code.build(&code.code_id()).unwrap();
+        let client_info = &server_info.client_info;
+        assert_eq!(client_info.stabilizer_checks.len(), 6);
+        assert_eq!(client_info.data_qubit_actions[6]["X"], vec![3, 4, 5]);
+        assert_eq!(client_info.data_qubit_actions[6]["Z"], vec![0, 1, 2]);
+        assert_eq!(client_info.data_qubit_actions[6]["Y"].len(), 6);
+        assert_eq!(server_info.solver_initializer.vertex_num, 6);
+        assert_eq!(server_info.solver_initializer.weighted_edges.len(), 21);
+    }
+
+    #[test]
+    fn test_parity_check_matrix_export() {
+        // cargo test -- test_parity_check_matrix_export --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        let h_z = code.client_info.parity_check_matrix("Z").unwrap();
+        assert_eq!(h_z.columns(), 9);
+        assert_eq!(h_z.sparse_rows().unwrap().len(), 4);
+        let alist = h_z.to_alist().unwrap();
+        println!("{}", alist);
+        let lines: Vec<&str> = alist.lines().collect();
+        assert_eq!(lines[0], "9 4");
+        assert_eq!(lines[1], "2 4");
+        assert_eq!(lines.len(), 4 + 9 + 4);
+        let mtx = h_z.to_matrix_market().unwrap();
+        assert!(mtx.lines().nth(1).unwrap() == "4 9 12");
+        // the exported matrices build the same code again
+        let rebuilt = ParityCheckCode {
+            h_x: Some(code.client_info.parity_check_matrix("X").unwrap()),
+            h_z: Some(h_z),
+            ..Default::default()
+        }
+        .build("rebuilt")
+        .unwrap();
+        assert_eq!(
+            rebuilt.solver_initializer.weighted_edges.len(),
+            code.solver_initializer.weighted_edges.len()
+        );
+        let five_qubit_code = crate::stabilizer::StabilizerCode {
+            stabilizers: vec!["XZZXI".to_string()],
+            ..Default::default()
+        };
+        let five_qubit_code = five_qubit_code.build("five").unwrap();
+        assert!(
+            five_qubit_code
+                .client_info
+                .parity_check_matrix("X")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parity_check_invalid_input() {
+        // cargo test -- test_parity_check_invalid_input --nocapture
+        let code = ParityCheckCode {
+            h: Some(BinaryMatrix::Dense(vec![vec![1, 1, 0], vec![0, 2, 1]])),
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+        let code = ParityCheckCode {
+            h: Some(BinaryMatrix::Sparse {
+                columns: 3,
+                rows: vec![vec![0, 3]],
+            }),
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+        // a declared size over the limit is rejected before anything is allocated per column
+        let code = ParityCheckCode {
+            h: Some(BinaryMatrix::Sparse {
+                columns: 1_000_000_000,
+                rows: vec![],
+            }),
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::codes::merged_weight;
+use crate::solver::SolverOptions;
+use mwpf::mwpf_solver::SolverTrait;
+use mwpf::util::*;
+use num_traits::Zero;
+use num_traits::cast::ToPrimitive;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// a connected component of the preprocessed hypergraph, decoded independently of the others
+#[derive(Debug, Clone)]
+pub struct GraphComponent {
+    pub solver_initializer: Arc<SolverInitializer>,
+    /// the vertex in the whole graph of each local vertex
+    pub vertices: Vec<usize>,
+    /// the edge in the preprocessed graph of each local edge
+    pub edges: Vec<usize>,
+}
+
+/// the decoding hypergraph after merging parallel hyperedges and dropping dominated ones
+#[derive(Debug, Clone)]
+pub struct PreprocessedGraph {
+    pub solver_initializer: Arc<SolverInitializer>,
+    /// the edge in the original graph that each hyperedge stands for, so that corrections map back to `edge_errors`
+    pub edge_origins: Vec<usize>,
+    pub components: Vec<GraphComponent>,
+    /// the component and the local vertex index of each vertex
+    pub vertex_components: Vec<(usize, usize)>,
+    /// the number of original hyperedges merged into a parallel one
+    pub merged_edges: usize,
+    /// the number of original hyperedges dropped because a cheaper combination has the same boundary
+    pub dominated_edges: usize,
+}
+
+/// the symmetric difference of two sorted vertex lists
+fn symmetric_difference(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = vec![];
+    while i < a.len() || j < b.len() {
+        if j == b.len() || (i < a.len() && a[i] < b[j]) {
+            result.push(a[i]);
+            i += 1;
+        } else if i == a.len() || b[j] < a[i] {
+            result.push(b[j]);
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+    result
+}
+
+fn find_root(parents: &mut [usize], mut vertex: usize) -> usize {
+    while parents[vertex] != vertex {
+        parents[vertex] = parents[parents[vertex]];
+        vertex = parents[vertex];
+    }
+    vertex
+}
+
+/// simplify the decoding hypergraph without changing the minimum weight of any syndrome:
+/// the parallel hyperedges are merged with the probability p = p1 (1 - p2) + p2 (1 - p1) and represented by
+/// the cheapest one; a hyperedge e is dropped if two remaining hyperedges a and b with a + b = e (as vertex sets
+/// over GF(2)) have w_a + w_b <= w_e, since any correction using e can use a and b instead; the hyperedges of
+/// no vertex are never needed. Finally, the graph is split into connected components.
+pub fn preprocess(initializer: &SolverInitializer) -> PreprocessedGraph {
+    let vertex_num = initializer.vertex_num;
+    // merge the parallel hyperedges
+    let mut parallel: BTreeMap<Vec<usize>, Vec<usize>> = BTreeMap::new();
+    for (edge_index, hyperedge) in initializer.weighted_edges.iter().enumerate() {
+        let mut vertices = hyperedge.vertices.clone();
+        vertices.sort();
+        vertices.dedup();
+        parallel.entry(vertices).or_default().push(edge_index);
+    }
+    let mut merged_edges = 0;
+    let mut dominated_edges = 0;
+    let mut hyperedges: Vec<(Vec<usize>, Rational, usize)> = vec![]; // (vertices, weight, origin)
+    for (vertices, edges) in parallel {
+        if vertices.is_empty() {
+            dominated_edges += edges.len();
+            continue;
+        }
+        let weight_of = |edge_index: usize| &initializer.weighted_edges[edge_index].weight;
+        let origin = *edges
+            .iter()
+            .min_by(|a, b| weight_of(**a).partial_cmp(weight_of(**b)).unwrap())
+            .unwrap();
+        let weight = if edges.len() == 1 {
+            weight_of(origin).clone()
+        } else {
+            merged_edges += edges.len() - 1;
+            merged_weight(
+                edges
+                    .iter()
+                    .map(|&edge_index| weight_of(edge_index).to_f64().unwrap()),
+            )
+            .into()
+        };
+        hyperedges.push((vertices, weight, origin));
+    }
+    hyperedges.sort_by_key(|(_, _, origin)| *origin);
+    // drop the dominated hyperedges, trying the most expensive ones first
+    let edge_by_vertices: HashMap<&[usize], usize> = hyperedges
+        .iter()
+        .enumerate()
+        .map(|(edge_index, (vertices, _, _))| (vertices.as_slice(), edge_index))
+        .collect();
+    let mut incident_edges: Vec<Vec<usize>> = vec![vec![]; vertex_num];
+    for (edge_index, (vertices, _, _)) in hyperedges.iter().enumerate() {
+        for &vertex in vertices.iter() {
+            incident_edges[vertex].push(edge_index);
+        }
+    }
+    let mut order: Vec<usize> = (0..hyperedges.len()).collect();
+    order.sort_by(|a, b| hyperedges[*b].1.partial_cmp(&hyperedges[*a].1).unwrap());
+    let mut is_dropped = vec![false; hyperedges.len()];
+    for edge_index in order {
+        let (vertices, weight, _) = &hyperedges[edge_index];
+        'search: for &vertex in vertices.iter() {
+            for &a in incident_edges[vertex].iter() {
+                if a == edge_index || is_dropped[a] {
+                    continue;
+                }
+                let complement = symmetric_difference(vertices, &hyperedges[a].0);
+                let Some(&b) = edge_by_vertices.get(complement.as_slice()) else {
+                    continue;
+                };
+                if b != edge_index
+                    && !is_dropped[b]
+                    && hyperedges[a].1.clone() + hyperedges[b].1.clone() <= *weight
+                {
+                    is_dropped[edge_index] = true;
+                    dominated_edges += 1;
+                    break 'search;
+                }
+            }
+        }
+    }
+    let hyperedges: Vec<(Vec<usize>, Rational, usize)> = hyperedges
+        .into_iter()
+        .zip(is_dropped)
+        .filter(|(_, is_dropped)| !is_dropped)
+        .map(|(hyperedge, _)| hyperedge)
+        .collect();
+    // split into connected components, numbered by their smallest vertex
+    let mut parents: Vec<usize> = (0..vertex_num).collect();
+    for (vertices, _, _) in hyperedges.iter() {
+        for &vertex in vertices.iter().skip(1) {
+            let (root_a, root_b) = (
+                find_root(&mut parents, vertices[0]),
+                find_root(&mut parents, vertex),
+            );
+            parents[root_a.max(root_b)] = root_a.min(root_b);
+        }
+    }
+    let mut components: Vec<GraphComponent> = vec![];
+    let mut component_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut vertex_components = Vec::with_capacity(vertex_num);
+    for vertex in 0..vertex_num {
+        let root = find_root(&mut parents, vertex);
+        let component_index = *component_of_root.entry(root).or_insert_with(|| {
+            components.push(GraphComponent {
+                solver_initializer: Arc::new(SolverInitializer::new(0, vec![])),
+                vertices: vec![],
+                edges: vec![],
+            });
+            components.len() - 1
+        });
+        let component = &mut components[component_index];
+        vertex_components.push((component_index, component.vertices.len()));
+        component.vertices.push(vertex);
+    }
+    let mut component_edges: Vec<Vec<HyperEdge>> = vec![vec![]; components.len()];
+    for (edge_index, (vertices, weight, _)) in hyperedges.iter().enumerate() {
+        let component_index = vertex_components[vertices[0]].0;
+        component_edges[component_index].push(HyperEdge::new(
+            vertices
+                .iter()
+                .map(|&vertex| vertex_components[vertex].1)
+                .collect(),
+            weight.clone(),
+        ));
+        components[component_index].edges.push(edge_index);
+    }
+    for (component, weighted_edges) in components.iter_mut().zip(component_edges) {
+        component.solver_initializer = Arc::new(SolverInitializer::new(
+            component.vertices.len(),
+            weighted_edges,
+        ));
+    }
+    PreprocessedGraph {
+        edge_origins: hyperedges.iter().map(|(_, _, origin)| *origin).collect(),
+        solver_initializer: Arc::new(SolverInitializer::new(
+            vertex_num,
+            hyperedges
+                .into_iter()
+                .map(|(vertices, weight, _)| HyperEdge::new(vertices, weight))
+                .collect(),
+        )),
+        components,
+        vertex_components,
+        merged_edges,
+        dominated_edges,
+    }
+}
+
+impl PreprocessedGraph {
+    /// decode each connected component with defects independently, returning the original edges of the
+    /// correction and the sum of the weight ranges of the components
+    pub fn decode(
+        &self,
+        defect_vertices: &[usize],
+        solver_options: &SolverOptions,
+    ) -> (Vec<usize>, WeightRange) {
+        self.decode_reusing(defect_vertices, solver_options, &mut HashMap::new())
+    }
+
+    /// [`Self::decode`] with the solvers of the components kept in `solvers` and cleared after each use,
+    /// so that the solvers are built once for many shots
+    pub fn decode_reusing(
+        &self,
+        defect_vertices: &[usize],
+        solver_options: &SolverOptions,
+        solvers: &mut HashMap<usize, Box<dyn SolverTrait>>,
+    ) -> (Vec<usize>, WeightRange) {
+        let mut component_defects: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for &vertex in defect_vertices.iter() {
+            let (component_index, local_vertex) = self.vertex_components[vertex];
+            component_defects
+                .entry(component_index)
+                .or_default()
+                .push(local_vertex);
+        }
+        let mut subgraph = vec![];
+        let (mut lower, mut upper) = (Rational::zero(), Rational::zero());
+        for (component_index, defects) in component_defects {
+            let component = &self.components[component_index];
+            let solver = solvers
+                .entry(component_index)
+                .or_insert_with(|| solver_options.build(&component.solver_initializer));
+            solver.solve_visualizer(SyndromePattern::new_vertices(defects), None);
+            let (local_subgraph, weight_range) = solver.subgraph_range_visualizer(None);
+            solver.clear();
+            subgraph.extend(
+                local_subgraph
+                    .iter()
+                    .map(|&local_edge| self.edge_origins[component.edges[local_edge]]),
+            );
+            lower += weight_range.lower;
+            upper += weight_range.upper;
+        }
+        subgraph.sort();
+        (subgraph, WeightRange::new(lower, upper))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codes::*;
+    use mwpf::mwpf_solver::{SolverSerialJointSingleHair, SolverTrait};
+    use serde_json::json;
+
+    #[test]
+    fn test_preprocess() {
+        // cargo test -- test_preprocess --nocapture
+        let initializer = SolverInitializer::new(
+            5,
+            vec![
+                HyperEdge::new(vec![0, 1], 1.0.into()),
+                HyperEdge::new(vec![1, 2], 1.0.into()),
+                HyperEdge::new(vec![2, 0], 3.0.into()), // dominated by the first two
+                HyperEdge::new(vec![1, 0], 1.0.into()), // parallel to the first one
+                HyperEdge::new(vec![3, 4], 1.0.into()),
+            ],
+        );
+        let graph = preprocess(&initializer);
+        assert_eq!(graph.merged_edges, 1);
+        assert_eq!(graph.dominated_edges, 1);
+        assert_eq!(graph.edge_origins, vec![0, 1, 4]);
+        assert_eq!(graph.components.len(), 2);
+        assert_eq!(graph.components[1].vertices, vec![3, 4]);
+        assert_eq!(graph.vertex_components[4], (1, 1));
+        // the merged weight is below the weight of each parallel edge
+        let merged_weight = graph.solver_initializer.weighted_edges[0]
+            .weight
+            .to_f64()
+            .unwrap();
+        assert!(merged_weight < 1.0 && merged_weight > 0.0);
+    }
+
+    #[test]
+    fn test_preprocess_decode() {
+        // cargo test -- test_preprocess_decode --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(5, NoiseType::Depolarize));
+        let graph = preprocess(&code.solver_initializer);
+        let initializer = Arc::new(code.solver_initializer.clone());
+        for defects in [vec![0], vec![1, 5], vec![2, 7, 13], vec![0, 23]] {
+            let mut solver = SolverSerialJointSingleHair::new(&initializer, json!({}));
+            solver.solve_visualizer(SyndromePattern::new_vertices(defects.clone()), None);
+            let (_, weight_range) = solver.subgraph_range_visualizer(None);
+            let (subgraph, preprocessed_range) = graph.decode(&defects, &SolverOptions::default());
+            // the correction is valid on the original graph and equally good
+            let mut flipped: BTreeMap<usize, bool> = BTreeMap::new();
+            for &edge_index in subgraph.iter() {
+                for &vertex in code.solver_initializer.weighted_edges[edge_index]
+                    .vertices
+                    .iter()
+                {
+                    *flipped.entry(vertex).or_insert(false) ^= true;
+                }
+            }
+            let flipped: Vec<usize> = flipped
+                .into_iter()
+                .filter(|(_, is_flipped)| *is_flipped)
+                .map(|(vertex, _)| vertex)
+                .collect();
+            assert_eq!(flipped, defects);
+            // both weight ranges contain the minimum weight
+            assert!(preprocessed_range.lower <= weight_range.upper);
+            assert!(weight_range.lower <= preprocessed_range.upper);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use crate::codes::{ClientCodeInfo, ServerCodeInfo};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone)]
+pub struct RegistryConfig {
+    /// a code expires if it is not used for this long
+    pub ttl: Duration,
+    /// the maximum total size of the registered codes, measured by their JSON length in bytes
+    pub quota: usize,
+    /// if specified, the codes are persisted in this directory and reloaded after restart
+    pub store_dir: Option<PathBuf>,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(24 * 3600),
+            quota: 256 * 1024 * 1024,
+            store_dir: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RegisteredCode {
+    code: Arc<ServerCodeInfo>,
+    size: usize,
+    expires_at: SystemTime,
+    /// the expiry in the store, which is rewritten once half of the lifetime is used
+    stored_expires_at: SystemTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredCode {
+    /// unix timestamp in seconds
+    expires_at: u64,
+    client_info: ClientCodeInfo,
+}
+
+#[derive(Debug, Default)]
+struct RegistryInner {
+    config: RegistryConfig,
+    codes: HashMap<String, RegisteredCode>,
+    total_size: usize,
+}
+
+/// codes registered at runtime, e.g. uploaded by users, looked up by their `code_id`;
+/// each code lives for `ttl` since its last use, and the least recently used codes are evicted
+/// when the total size exceeds the quota
+#[derive(Debug, Default)]
+pub struct CodeRegistry {
+    inner: RwLock<RegistryInner>,
+}
+
+impl CodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// apply the configuration and load the persisted codes, returning the number of loaded codes
+    pub fn configure(&self, config: RegistryConfig) -> std::io::Result<usize> {
+        let mut inner = self.inner.write().unwrap();
+        inner.config = config;
+        let Some(store_dir) = inner.config.store_dir.clone() else {
+            return Ok(0);
+        };
+        std::fs::create_dir_all(&store_dir)?;
+        let now = SystemTime::now();
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(&store_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let stored: StoredCode = match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            {
+                Ok(stored) => stored,
+                Err(error) => {
+                    log::warn!("Skip invalid stored code {:?}: {}", path, error);
+                    continue;
+                }
+            };
+            let expires_at = UNIX_EPOCH + Duration::from_secs(stored.expires_at);
+            if expires_at <= now {
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+            let size = serde_json::to_string(&stored.client_info).unwrap().len();
+            let code = Arc::new(ServerCodeInfo::from_client_info(stored.client_info));
+            inner.total_size += size;
+            if let Some(previous) = inner.codes.insert(
+                code.client_info.id.clone(),
+                RegisteredCode {
+                    code,
+                    size,
+                    expires_at,
+                    stored_expires_at: expires_at,
+                },
+            ) {
+                inner.total_size -= previous.size;
+            }
+            loaded += 1;
+        }
+        // the quota may be smaller than before the restart
+        let evicted = inner.evict_to_fit(0);
+        Ok(loaded.saturating_sub(evicted))
+    }
+
+    /// register a code under its `client_info.id`; if a code with the same id is registered, it is returned
+    /// when its content is the same, and the new code is refused otherwise
+    pub fn insert(&self, code: ServerCodeInfo) -> Result<Arc<ServerCodeInfo>, String> {
+        let size = serde_json::to_string(&code.client_info).unwrap().len();
+        let code_id = code.client_info.id.clone();
+        let mut inner = self.inner.write().unwrap();
+        inner.remove_expired();
+        if let Some(registered) = inner.codes.get(&code_id) {
+            // compared as JSON values, since the maps of the client information serialize in any order
+            if serde_json::to_value(&registered.code.client_info).unwrap()
+                != serde_json::to_value(&code.client_info).unwrap()
+            {
+                return Err(format!(
+                    "a different code is already registered as {}",
+                    code_id
+                ));
+            }
+            drop(inner);
+            // extend the lifetime of the existing code as if it were used
+            return self
+                .get(&code_id)
+                .ok_or(format!("code {} just expired", code_id));
+        }
+        if size > inner.config.quota {
+            return Err(format!(
+                "the code has size {} bytes, exceeding the quota of {} bytes",
+                size, inner.config.quota
+            ));
+        }
+        inner.evict_to_fit(size);
+        let expires_at = SystemTime::now() + inner.config.ttl;
+        let code = Arc::new(code);
+        let store_path = inner.store_path(&code_id);
+        inner.total_size += size;
+        inner.codes.insert(
+            code_id,
+            RegisteredCode {
+                code: code.clone(),
+                size,
+                expires_at,
+                stored_expires_at: expires_at,
+            },
+        );
+        // the file is written without holding the lock, so that other requests are not blocked on the disk
+        drop(inner);
+        if let Some(path) = store_path {
+            persist(&path, &code, expires_at);
+        }
+        Ok(code)
+    }
+
+    /// get a code that has not expired and extend its lifetime
+    pub fn get(&self, code_id: &str) -> Option<Arc<ServerCodeInfo>> {
+        let mut inner = self.inner.write().unwrap();
+        let now = SystemTime::now();
+        let ttl = inner.config.ttl;
+        let registered = inner.codes.get_mut(code_id)?;
+        if registered.expires_at <= now {
+            inner.remove(code_id);
+            return None;
+        }
+        registered.expires_at = now + ttl;
+        let code = registered.code.clone();
+        // keep the stored expiry close enough, so that a code in use survives a restart
+        if registered.stored_expires_at <= now + ttl / 2 {
+            registered.stored_expires_at = registered.expires_at;
+            let expires_at = registered.expires_at;
+            if let Some(path) = inner.store_path(code_id) {
+                drop(inner);
+                persist(&path, &code, expires_at);
+            }
+        }
+        Some(code)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// write a code to the store with its expiry
+fn persist(path: &PathBuf, code: &ServerCodeInfo, expires_at: SystemTime) {
+    let stored = StoredCode {
+        expires_at: expires_at.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        client_info: code.client_info.clone(),
+    };
+    if let Err(error) = std::fs::write(path, serde_json::to_string(&stored).unwrap()) {
+        log::error!(
+            "Failed to persist code {} to {:?}: {}",
+            code.client_info.id,
+            path,
+            error
+        );
+    }
+}
+
+impl RegistryInner {
+    /// evict the least recently used codes until a new code of `size` fits, returning the number of evicted codes
+    fn evict_to_fit(&mut self, size: usize) -> usize {
+        let mut evicted = 0;
+        while self.total_size + size > self.config.quota && !self.codes.is_empty() {
+            let lru_id = self
+                .codes
+                .iter()
+                .min_by_key(|(_, registered)| registered.expires_at)
+                .map(|(code_id, _)| code_id.clone())
+                .unwrap();
+            log::info!("Evict code {} due to the size quota", lru_id);
+            self.remove(&lru_id);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    fn store_path(&self, code_id: &str) -> Option<PathBuf> {
+        self.config
+            .store_dir
+            .as_ref()
+            .map(|store_dir| store_dir.join(format!("{}.json", code_id)))
+    }
+
+    fn remove(&mut self, code_id: &str) {
+        if let Some(registered) = self.codes.remove(code_id) {
+            self.total_size -= registered.size;
+            if let Some(path) = self.store_path(code_id) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    fn remove_expired(&mut self) {
+        let now = SystemTime::now();
+        let expired: Vec<String> = self
+            .codes
+            .iter()
+            .filter(|(_, registered)| registered.expires_at <= now)
+            .map(|(code_id, _)| code_id.clone())
+            .collect();
+        for code_id in expired {
+            self.remove(&code_id);
+        }
+    }
+}
+
+/// generate a code id from the content of the upload, so that uploading the same code twice gives the same id;
+/// the hash is the first 128 bits of SHA-256, which stays the same across restarts and Rust releases
+pub fn content_code_id(prefix: &str, content: &impl Serialize) -> String {
+    let digest = Sha256::digest(
+        serde_json::to_string(content)
+            .expect("content must be serializable")
+            .as_bytes(),
+    );
+    let hash: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}-{}", prefix, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stabilizer::StabilizerCode;
+
+    fn repetition_code(n: usize) -> ServerCodeInfo {
+        let code = StabilizerCode {
+            stabilizers: (0..n - 1)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| if j == i || j == i + 1 { 'Z' } else { 'I' })
+                        .collect::<String>()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        code.build(&code.code_id()).unwrap()
+    }
+
+    #[test]
+    fn test_registry_quota_and_ttl() {
+        // cargo test -- test_registry_quota_and_ttl --nocapture
+        let registry = CodeRegistry::new();
+        let small = repetition_code(3);
+        let small_size = serde_json::to_string(&small.client_info).unwrap().len();
+        registry
+            .configure(RegistryConfig {
+                quota: small_size * 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(registry.insert(repetition_code(30)).is_err());
+        let small_id = registry.insert(small).unwrap().client_info.id.clone();
+        assert!(registry.get(&small_id).is_some());
+        // inserting another code evicts the least recently used one
+        let other_id = registry
+            .insert(repetition_code(4))
+            .unwrap()
+            .client_info
+            .id
+            .clone();
+        assert!(registry.get(&small_id).is_none());
+        assert!(registry.get(&other_id).is_some());
+        // expired codes are no longer available
+        registry
+            .configure(RegistryConfig {
+                ttl: Duration::ZERO,
+                ..Default::default()
+            })
+            .unwrap();
+        let small_id = registry
+            .insert(repetition_code(3))
+            .unwrap()
+            .client_info
+            .id
+            .clone();
+        assert!(registry.get(&small_id).is_none());
+    }
+
+    #[test]
+    fn test_registry_same_id() {
+        // cargo test -- test_registry_same_id --nocapture
+        // the ids do not change across releases
+        assert_eq!(
+            content_code_id("test", &"abc"),
+            "test-6cc43f858fbb763301637b5af970e2a4"
+        );
+        let registry = CodeRegistry::new();
+        let code = registry.insert(repetition_code(3)).unwrap();
+        // uploading the same code again gives the registered one
+        let again = registry.insert(repetition_code(3)).unwrap();
+        assert!(Arc::ptr_eq(&code, &again));
+        assert_eq!(registry.len(), 1);
+        // a different code under the same id is refused
+        let mut other = repetition_code(4);
+        other.client_info.id = code.client_info.id.clone();
+        assert!(registry.insert(other).is_err());
+        assert!(Arc::ptr_eq(
+            &registry.get(&code.client_info.id).unwrap(),
+            &code
+        ));
+    }
+
+    #[test]
+    fn test_registry_store() {
+        // cargo test -- test_registry_store --nocapture
+        let store_dir = std::env::temp_dir().join(format!("mwpf-registry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&store_dir);
+        let stored_expiry = |code_id: &str| {
+            let content = std::fs::read_to_string(store_dir.join(format!("{}.json", code_id)));
+            serde_json::from_str::<StoredCode>(&content.unwrap())
+                .unwrap()
+                .expires_at
+        };
+        let config = RegistryConfig {
+            ttl: Duration::from_secs(2),
+            store_dir: Some(store_dir.clone()),
+            ..Default::default()
+        };
+        let registry = CodeRegistry::new();
+        registry.configure(config.clone()).unwrap();
+        let small_id = registry
+            .insert(repetition_code(3))
+            .unwrap()
+            .client_info
+            .id
+            .clone();
+        let other_id = registry
+            .insert(repetition_code(4))
+            .unwrap()
+            .client_info
+            .id
+            .clone();
+        // using a code after half of its lifetime refreshes the stored expiry
+        let expiry = stored_expiry(&small_id);
+        std::thread::sleep(Duration::from_millis(1200));
+        assert!(registry.get(&small_id).is_some());
+        assert!(stored_expiry(&small_id) > expiry);
+        // the codes loaded over a smaller quota are evicted, the least recently used first
+        let small_size = serde_json::to_string(&repetition_code(3).client_info)
+            .unwrap()
+            .len();
+        let reloaded = CodeRegistry::new();
+        let loaded = reloaded
+            .configure(RegistryConfig {
+                quota: small_size,
+                ..config
+            })
+            .unwrap();
+        assert_eq!(loaded, 1);
+        assert!(reloaded.get(&small_id).is_some());
+        assert!(reloaded.get(&other_id).is_none());
+        let _ = std::fs::remove_dir_all(&store_dir);
+    }
+}
@@ -0,0 +1,199 @@
+use mwpf::mwpf_solver::*;
+use mwpf::util::SolverInitializer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+/// the largest cluster node limit accepted from a client
+pub const MAX_CLUSTER_NODE_LIMIT: usize = 10_000;
+/// the longest solver timeout in seconds accepted from a client
+pub const MAX_SOLVER_TIMEOUT: f64 = 10.0;
+
+/// the solvers of mwpf, from the fastest to the most accurate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolverType {
+    /// the union-find decoder, which grows the clusters without tuning; an approximation of MWPF
+    UnionFind,
+    /// MWPF tuning a single hair of a cluster at a time
+    SingleHair,
+    /// MWPF tuning the single hairs of a cluster jointly
+    #[default]
+    JointSingleHair,
+}
+
+impl SolverType {
+    pub const ALL: [SolverType; 3] = [
+        SolverType::UnionFind,
+        SolverType::SingleHair,
+        SolverType::JointSingleHair,
+    ];
+
+    /// the config options passed through to the solver; any other option is rejected
+    pub fn allowed_options(&self) -> &'static [&'static str] {
+        match self {
+            SolverType::UnionFind => &["timeout"],
+            SolverType::SingleHair | SolverType::JointSingleHair => {
+                &["cluster_node_limit", "timeout"]
+            }
+        }
+    }
+}
+
+/// a solver together with its validated config
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverOptions {
+    pub solver: SolverType,
+    pub config: serde_json::Value,
+}
+
+impl Default for SolverOptions {
+    /// the default solver with the default config of mwpf
+    fn default() -> Self {
+        Self {
+            solver: SolverType::default(),
+            config: json!({}),
+        }
+    }
+}
+
+impl SolverOptions {
+    /// check the config of a client against the allowed options of the solver and the caps of the server;
+    /// `cluster_node_limit` applies to the solvers that take it, unless the config gives its own
+    pub fn new(
+        solver: SolverType,
+        cluster_node_limit: usize,
+        config: Option<&serde_json::Value>,
+    ) -> Result<Self, String> {
+        let allowed_options = solver.allowed_options();
+        let mut solver_config = serde_json::Map::new();
+        if allowed_options.contains(&"cluster_node_limit") {
+            solver_config.insert("cluster_node_limit".to_string(), json!(cluster_node_limit));
+        }
+        if let Some(config) = config {
+            let Some(config) = config.as_object() else {
+                return Err("the solver config must be a JSON object".to_string());
+            };
+            for (option, value) in config.iter() {
+                if !allowed_options.contains(&option.as_str()) {
+                    return Err(format!(
+                        "option {} is not allowed for solver {:?}, expect one of {:?}",
+                        option, solver, allowed_options
+                    ));
+                }
+                solver_config.insert(option.clone(), value.clone());
+            }
+        }
+        if let Some(value) = solver_config.get("cluster_node_limit").filter(|value| {
+            !value
+                .as_u64()
+                .is_some_and(|limit| limit <= MAX_CLUSTER_NODE_LIMIT as u64)
+        }) {
+            return Err(format!(
+                "cluster_node_limit {} is not an integer up to {}",
+                value, MAX_CLUSTER_NODE_LIMIT
+            ));
+        }
+        if let Some(value) = solver_config.get("timeout").filter(|value| {
+            !value
+                .as_f64()
+                .is_some_and(|timeout| timeout > 0.0 && timeout <= MAX_SOLVER_TIMEOUT)
+        }) {
+            return Err(format!(
+                "timeout {} is not a number of seconds in (0, {}]",
+                value, MAX_SOLVER_TIMEOUT
+            ));
+        }
+        Ok(Self {
+            solver,
+            config: serde_json::Value::Object(solver_config),
+        })
+    }
+
+    pub fn build(&self, initializer: &Arc<SolverInitializer>) -> Box<dyn SolverTrait> {
+        let config = self.config.clone();
+        match self.solver {
+            SolverType::UnionFind => Box::new(SolverSerialUnionFind::new(initializer, config)),
+            SolverType::SingleHair => Box::new(SolverSerialSingleHair::new(initializer, config)),
+            SolverType::JointSingleHair => {
+                Box::new(SolverSerialJointSingleHair::new(initializer, config))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codes::*;
+    use mwpf::util::SyndromePattern;
+    use num_traits::cast::ToPrimitive;
+
+    #[test]
+    fn test_solver_options() {
+        // cargo test -- test_solver_options --nocapture
+        let options = SolverOptions::new(SolverType::default(), 200, None).unwrap();
+        assert_eq!(options.config, json!({"cluster_node_limit": 200}));
+        let options = SolverOptions::new(
+            SolverType::SingleHair,
+            200,
+            Some(&json!({"cluster_node_limit": 50, "timeout": 1.5})),
+        )
+        .unwrap();
+        assert_eq!(
+            options.config,
+            json!({"cluster_node_limit": 50, "timeout": 1.5})
+        );
+        let options = SolverOptions::new(SolverType::UnionFind, 200, None).unwrap();
+        assert_eq!(options.config, json!({}));
+        // the configs out of the allow-lists and the caps
+        for (solver, config) in [
+            (SolverType::UnionFind, json!({"cluster_node_limit": 50})),
+            (SolverType::JointSingleHair, json!({"unknown": 1})),
+            (
+                SolverType::JointSingleHair,
+                json!({"cluster_node_limit": -1}),
+            ),
+            (
+                SolverType::JointSingleHair,
+                json!({"cluster_node_limit": 1e9}),
+            ),
+            (SolverType::JointSingleHair, json!({"timeout": 3600})),
+            (SolverType::JointSingleHair, json!({"timeout": "1"})),
+            (SolverType::JointSingleHair, json!([1])),
+        ] {
+            assert!(SolverOptions::new(solver, 200, Some(&config)).is_err());
+        }
+        assert!(SolverOptions::new(SolverType::default(), 1_000_000, None).is_err());
+        assert_eq!(
+            serde_json::from_str::<SolverType>("\"union-find\"").unwrap(),
+            SolverType::UnionFind
+        );
+    }
+
+    #[test]
+    fn test_solvers() {
+        // cargo test -- test_solvers --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let initializer = Arc::new(code.solver_initializer.clone());
+        let edge_index = code.solver_initializer.weighted_edges.len() / 2;
+        let defects = code.solver_initializer.weighted_edges[edge_index]
+            .vertices
+            .clone();
+        // every solver corrects a single error
+        for solver in SolverType::ALL {
+            let options = SolverOptions::new(solver, 200, None).unwrap();
+            let mut solver = options.build(&initializer);
+            solver.solve_visualizer(SyndromePattern::new_vertices(defects.clone()), None);
+            let (subgraph, weight_range) = solver.subgraph_range_visualizer(None);
+            assert_eq!(subgraph, vec![edge_index]);
+            assert_eq!(
+                weight_range.upper.to_f64().unwrap(),
+                code.solver_initializer.weighted_edges[edge_index]
+                    .weight
+                    .to_f64()
+                    .unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::codes::*;
+use crate::layout::*;
+use serde::{Deserialize, Serialize};
+
+const MAX_STABILIZER_QUBITS: usize = 10_000;
+const MAX_STABILIZERS: usize = 10_000;
+/// the maximum number of non-identity terms in all the stabilizers and logical observables together
+const MAX_STABILIZER_WEIGHT: usize = 200_000;
+
+/// parse a Pauli string like `XZZXI` into its non-identity terms (data_qubit_index, check_type);
+/// a leading sign like `-` or `+i` is accepted and ignored, and `_` can be used in place of `I`
+pub fn parse_pauli_string(pauli: &str) -> Result<(usize, Vec<(usize, String)>), String> {
+    let body = pauli.trim();
+    let body = body
+        .strip_prefix(['+', '-'])
+        .map(|body| body.strip_prefix('i').unwrap_or(body))
+        .unwrap_or(body);
+    let mut terms = vec![];
+    let mut length = 0;
+    for (data_index, c) in body.chars().enumerate() {
+        match c {
+            'I' | '_' => {}
+            'X' => terms.push((data_index, "X".to_string())),
+            'Y' => terms.push((data_index, "Y".to_string())),
+            'Z' => terms.push((data_index, "Z".to_string())),
+            _ => {
+                return Err(format!(
+                    "invalid character '{}' in Pauli string \"{}\"",
+                    c, pauli
+                ));
+            }
+        }
+        length += 1;
+    }
+    Ok((length, terms))
+}
+
+/// a general (possibly non-CSS) stabilizer code given by its generators as Pauli strings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StabilizerCode {
+    pub name: Option<String>,
+    /// the code distance if known, otherwise 0
+    #[serde(default)]
+    pub d: usize,
+    pub stabilizers: Vec<String>,
+    #[serde(default)]
+    pub logical_observables: Vec<String>,
+}
+
+impl StabilizerCode {
+    /// the code id derived from the content of the stabilizers
+    pub fn code_id(&self) -> String {
+        crate::registry::content_code_id("stab", self)
+    }
+
+    pub fn build_client_info(&self, id: &str) -> Result<ClientCodeInfo, String> {
+        if self.stabilizers.len() > MAX_STABILIZERS {
+            return Err(format!(
+                "{} stabilizers exceed the limit of {}",
+                self.stabilizers.len(),
+                MAX_STABILIZERS
+            ));
+        }
+        let mut data_qubit_num = None;
+        let mut weight = 0;
+        let mut parse = |pauli: &String| -> Result<Vec<(usize, String)>, String> {
+            let (length, terms) = parse_pauli_string(pauli)?;
+            if length > MAX_STABILIZER_QUBITS {
+                return Err(format!(
+                    "Pauli string of length {} exceeds the limit of {} qubits",
+                    length, MAX_STABILIZER_QUBITS
+                ));
+            }
+            weight += terms.len();
+            if weight > MAX_STABILIZER_WEIGHT {
+                return Err(format!(
+                    "the Pauli strings have more than {} non-identity terms in total",
+                    MAX_STABILIZER_WEIGHT
+                ));
+            }
+            match data_qubit_num {
+                None => data_qubit_num = Some(length),
+                Some(n) if n != length => {
+                    return Err(format!(
+                        "Pauli string \"{}\" has length {}, expected {}",
+                        pauli, length, n
+                    ));
+                }
+                _ => {}
+            }
+            Ok(terms)
+        };
+        let stabilizer_checks = self
+            .stabilizers
+            .iter()
+            .map(&mut parse)
+            .collect::<Result<Vec<_>, String>>()?;
+        let logical_observables = self
+            .logical_observables
+            .iter()
+            .map(&mut parse)
+            .collect::<Result<Vec<_>, String>>()?;
+        let data_qubit_num = data_qubit_num.unwrap_or(0);
+        if data_qubit_num == 0 {
+            return Err("no stabilizer is given".to_string());
+        }
+        let layout = auto_layout(data_qubit_num, &stabilizer_checks);
+        let mut client_info = ClientCodeInfo {
+            id: id.to_string(),
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Stabilizer Code (n={})", data_qubit_num)),
+            d: self.d,
+            data_qubit_actions: data_qubit_actions_from_checks(
+                data_qubit_num,
+                &stabilizer_checks,
+                &["X", "Y", "Z"],
+            ),
+            data_qubit_positions: layout.data_qubit_positions,
+            stabilizer_positions: layout.stabilizer_positions,
+            stabilizer_shapes: layout.stabilizer_shapes,
+            stabilizer_colors: stabilizer_checks
+                .iter()
+                .map(|check| stabilizer_color(check))
+                .collect(),
+            stabilizer_checks,
+            logical_observables,
+            error_probabilities: vec![],
+            correlated_errors: vec![],
+            is_detector_error_model: false,
+            detector_coordinates: vec![],
+        };
+        client_info.fill_logical_observables();
+        Ok(client_info)
+    }
+
+    pub fn build(&self, id: &str) -> Result<ServerCodeInfo, String> {
+        Ok(ServerCodeInfo::from_client_info(
+            self.build_client_info(id)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_five_qubit_code() {
+        // cargo test -- test_five_qubit_code --nocapture
+        let code = StabilizerCode {
+            name: Some("Five-Qubit Code".to_string()),
+            d: 3,
+            stabilizers: ["XZZXI", "IXZZX", "XIXZZ", "ZXIXZ"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            logical_observables: vec!["XXXXX".to_string(), "ZZZZZ".to_string()],
+        };
+        let server_info = code.build(&code.code_id()).unwrap();
+        let client_info = &server_info.client_info;
+        // qubit 1 is acted on by Z, X, I, X in the four stabilizers
+        assert_eq!(client_info.data_qubit_actions[1]["X"], vec![0]);
+        assert_eq!(client_info.data_qubit_actions[1]["Z"], vec![1, 3]);
+        assert_eq!(client_info.data_qubit_actions[1]["Y"], vec![0, 1, 3]);
+        // the five-qubit code is perfect: all 15 single-qubit errors have distinct nontrivial syndromes
+        assert_eq!(server_info.solver_initializer.weighted_edges.len(), 15);
+        assert_eq!(client_info.logical_observables[1].len(), 5);
+    }
+
+    #[test]
+    fn test_invalid_pauli_string() {
+        // cargo test -- test_invalid_pauli_string --nocapture
+        assert!(parse_pauli_string("XQZ").is_err());
+        assert_eq!(parse_pauli_string("-iX_Z").unwrap().1.len(), 2);
+        let code = StabilizerCode {
+            stabilizers: vec!["XX".to_string(), "ZZZ".to_string()],
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+        // codes beyond the limits are rejected before laying them out
+        let code = StabilizerCode {
+            stabilizers: vec!["I".repeat(MAX_STABILIZER_QUBITS + 1)],
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+        let code = StabilizerCode {
+            stabilizers: vec!["X".to_string(); MAX_STABILIZERS + 1],
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+        let code = StabilizerCode {
+            stabilizers: vec!["X".repeat(MAX_STABILIZER_QUBITS); 100],
+            ..Default::default()
+        };
+        assert!(code.build("invalid").is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::codes::{ClientCodeInfo, pauli_anticommute};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+fn targets(qubits: impl Iterator<Item = usize>) -> String {
+    qubits
+        .map(|qubit| qubit.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl ClientCodeInfo {
+    /// the step of each term in the hook-aware order of the rotated surface code: X checks visit their
+    /// data qubits row by row and Z checks column by column, so that the neighboring X and Z checks
+    /// touch their shared qubits in the same order; `None` if a check is not a pure X or Z check with
+    /// at most one data qubit in each diagonal direction
+    fn stim_geometric_steps(&self) -> Option<Vec<Vec<usize>>> {
+        self.stabilizer_checks
+            .iter()
+            .zip(self.stabilizer_positions.iter())
+            .map(|(check, &(si, sj))| {
+                let is_column_major = match check.first().map(|(_, check_type)| check_type.as_str())
+                {
+                    Some("X") => false,
+                    Some("Z") => true,
+                    _ => return None,
+                };
+                let mut steps = vec![];
+                for (data_index, check_type) in check.iter() {
+                    let (di, dj) = self.data_qubit_positions[*data_index];
+                    if check_type != &check[0].1 || di == si || dj == sj {
+                        return None;
+                    }
+                    let (row, column) = ((di > si) as usize, (dj > sj) as usize);
+                    let step = if is_column_major {
+                        2 * column + row
+                    } else {
+                        2 * row + column
+                    };
+                    if steps.contains(&step) {
+                        return None;
+                    }
+                    steps.push(step);
+                }
+                Some(steps)
+            })
+            .collect()
+    }
+
+    /// whether the gates applied in the given order measure the stabilizers faithfully: every pair of checks
+    /// must act on the qubits where they anticommute in the same relative order an even number of times
+    fn stim_is_commuting_order(&self, gates: &[(usize, usize, String)]) -> bool {
+        let mut on_qubit: Vec<Vec<(usize, &str)>> = vec![vec![]; self.data_qubit_positions.len()];
+        let mut first_counts: HashMap<(usize, usize), usize> = HashMap::new();
+        for (ancilla, data_index, check_type) in gates.iter() {
+            for &(earlier, earlier_type) in on_qubit[*data_index].iter() {
+                if pauli_anticommute(earlier_type, check_type) {
+                    *first_counts.entry((earlier, *ancilla)).or_insert(0) += 1;
+                }
+            }
+            on_qubit[*data_index].push((*ancilla, check_type.as_str()));
+        }
+        // the anticommuting positions of commuting checks are even, so the order is consistent if and only if
+        // each check comes first on an even number of them
+        first_counts.values().all(|count| count % 2 == 0)
+    }
+
+    /// schedule the controlled-Pauli gates of the stabilizer measurements into layers where each qubit
+    /// is used at most once, keeping the order of the gates on each qubit; the hook-aware order is used
+    /// when it applies, otherwise the k-th term of every check is applied before its (k+1)-th term, and
+    /// if that fails to commute, the checks touch every data qubit in the order of their indices
+    fn stim_gate_layers(&self) -> Vec<Vec<(usize, usize, String)>> {
+        let data_qubit_num = self.data_qubit_positions.len();
+        let term_steps: Vec<Vec<usize>> = self
+            .stabilizer_checks
+            .iter()
+            .map(|check| (0..check.len()).collect())
+            .collect();
+        let gates_in_order = |steps: &[Vec<usize>], by_stabilizer: bool| {
+            let mut keyed: Vec<((usize, usize), (usize, usize, String))> = vec![];
+            for (stabilizer_idx, check) in self.stabilizer_checks.iter().enumerate() {
+                for ((data_index, check_type), &step) in
+                    check.iter().zip(steps[stabilizer_idx].iter())
+                {
+                    let key = if by_stabilizer {
+                        (stabilizer_idx, step)
+                    } else {
+                        (step, stabilizer_idx)
+                    };
+                    keyed.push((
+                        key,
+                        (
+                            data_qubit_num + stabilizer_idx,
+                            *data_index,
+                            check_type.clone(),
+                        ),
+                    ));
+                }
+            }
+            keyed.sort_by_key(|(key, _)| *key);
+            keyed.into_iter().map(|(_, gate)| gate).collect::<Vec<_>>()
+        };
+        let gates = [
+            self.stim_geometric_steps()
+                .map(|steps| gates_in_order(&steps, false)),
+            Some(gates_in_order(&term_steps, false)),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|gates| self.stim_is_commuting_order(gates))
+        .unwrap_or_else(|| gates_in_order(&term_steps, true));
+        // place each gate right after the last gates on its qubits, which keeps the order on every qubit
+        let mut next_layer = vec![0; data_qubit_num + self.stabilizer_positions.len()];
+        let mut layers: Vec<Vec<(usize, usize, String)>> = vec![];
+        for (ancilla, data_index, check_type) in gates {
+            let layer = next_layer[ancilla].max(next_layer[data_index]);
+            if layer == layers.len() {
+                layers.push(vec![]);
+            }
+            layers[layer].push((ancilla, data_index, check_type));
+            next_layer[ancilla] = layer + 1;
+            next_layer[data_index] = layer + 1;
+        }
+        layers
+    }
+
+    /// one round of stabilizer measurements, each using an ancilla prepared in |+> and controlled-Pauli gates
+    fn write_stim_round(&self, circuit: &mut String, p: f64, is_first: bool, basis: &str) {
+        let data_qubit_num = self.data_qubit_positions.len();
+        let stabilizer_num = self.stabilizer_positions.len();
+        let data_targets = targets(0..data_qubit_num);
+        let ancilla_targets = targets(data_qubit_num..data_qubit_num + stabilizer_num);
+        writeln!(circuit, "RX {}", ancilla_targets).unwrap();
+        writeln!(circuit, "Z_ERROR({}) {}", p, ancilla_targets).unwrap();
+        writeln!(circuit, "DEPOLARIZE1({}) {}", p, data_targets).unwrap();
+        writeln!(circuit, "TICK").unwrap();
+        for layer in self.stim_gate_layers() {
+            for gate in ["X", "Y", "Z"] {
+                let pairs: Vec<String> = layer
+                    .iter()
+                    .filter(|(_, _, check_type)| check_type == gate)
+                    .map(|(ancilla, data_index, _)| format!("{} {}", ancilla, data_index))
+                    .collect();
+                if !pairs.is_empty() {
+                    writeln!(circuit, "C{} {}", gate, pairs.join(" ")).unwrap();
+                }
+            }
+            let pairs: Vec<String> = layer
+                .iter()
+                .map(|(ancilla, data_index, _)| format!("{} {}", ancilla, data_index))
+                .collect();
+            writeln!(circuit, "DEPOLARIZE2({}) {}", p, pairs.join(" ")).unwrap();
+            writeln!(circuit, "TICK").unwrap();
+        }
+        writeln!(circuit, "Z_ERROR({}) {}", p, ancilla_targets).unwrap();
+        writeln!(circuit, "MX {}", ancilla_targets).unwrap();
+        for (stabilizer_idx, check) in self.stabilizer_checks.iter().enumerate() {
+            let (i, j) = self.stabilizer_positions[stabilizer_idx];
+            let lookback = stabilizer_num - stabilizer_idx;
+            if !is_first {
+                writeln!(
+                    circuit,
+                    "DETECTOR({}, {}, 0) rec[-{}] rec[-{}]",
+                    i,
+                    j,
+                    lookback,
+                    lookback + stabilizer_num
+                )
+                .unwrap();
+            } else if check.iter().all(|(_, check_type)| check_type == basis) {
+                // only the stabilizers in the preparation basis have deterministic outcomes in the first round
+                writeln!(circuit, "DETECTOR({}, {}, 0) rec[-{}]", i, j, lookback).unwrap();
+            }
+        }
+        writeln!(circuit, "SHIFT_COORDS(0, 0, 1)").unwrap();
+    }
+
+    /// generate a Stim circuit of a memory experiment in the given basis ("X" or "Z") under circuit-level
+    /// noise of strength `p`; only the logical observables entirely in that basis are included
+    pub fn stim_memory_circuit(
+        &self,
+        p: f64,
+        rounds: usize,
+        basis: &str,
+    ) -> Result<String, String> {
+        self.check_consistency()?;
+        if !(0.0..=0.5).contains(&p) {
+            return Err(format!("the physical error rate {} is not in [0, 0.5]", p));
+        }
+        if rounds == 0 {
+            return Err("at least one round of measurement is needed".to_string());
+        }
+        if basis != "X" && basis != "Z" {
+            return Err(format!("the basis {} is not X or Z", basis));
+        }
+        let data_qubit_num = self.data_qubit_positions.len();
+        let stabilizer_num = self.stabilizer_positions.len();
+        let basis_suffix = if basis == "X" { "X" } else { "" };
+        let flip_error = if basis == "X" { "Z_ERROR" } else { "X_ERROR" };
+        let mut circuit = String::new();
+        writeln!(
+            circuit,
+            "# memory experiment of {} in the {} basis",
+            self.name, basis
+        )
+        .unwrap();
+        for (data_index, (i, j)) in self.data_qubit_positions.iter().enumerate() {
+            writeln!(circuit, "QUBIT_COORDS({}, {}) {}", i, j, data_index).unwrap();
+        }
+        for (stabilizer_idx, (i, j)) in self.stabilizer_positions.iter().enumerate() {
+            writeln!(
+                circuit,
+                "QUBIT_COORDS({}, {}) {}",
+                i,
+                j,
+                data_qubit_num + stabilizer_idx
+            )
+            .unwrap();
+        }
+        let data_targets = targets(0..data_qubit_num);
+        writeln!(circuit, "R{} {}", basis_suffix, data_targets).unwrap();
+        writeln!(circuit, "{}({}) {}", flip_error, p, data_targets).unwrap();
+        self.write_stim_round(&mut circuit, p, true, basis);
+        if rounds > 1 {
+            writeln!(circuit, "REPEAT {} {{", rounds - 1).unwrap();
+            let mut body = String::new();
+            self.write_stim_round(&mut body, p, false, basis);
+            for line in body.lines() {
+                writeln!(circuit, "    {}", line).unwrap();
+            }
+            writeln!(circuit, "}}").unwrap();
+        }
+        // measure the data qubits and compare the stabilizers with their last measurement
+        writeln!(circuit, "{}({}) {}", flip_error, p, data_targets).unwrap();
+        writeln!(circuit, "M{} {}", basis_suffix, data_targets).unwrap();
+        let data_lookback = |data_index: usize| format!("rec[-{}]", data_qubit_num - data_index);
+        for (stabilizer_idx, check) in self.stabilizer_checks.iter().enumerate() {
+            if check.is_empty() || !check.iter().all(|(_, check_type)| check_type == basis) {
+                continue;
+            }
+            let (i, j) = self.stabilizer_positions[stabilizer_idx];
+            let mut records: Vec<String> = check
+                .iter()
+                .map(|(data_index, _)| data_lookback(*data_index))
+                .collect();
+            records.push(format!(
+                "rec[-{}]",
+                data_qubit_num + stabilizer_num - stabilizer_idx
+            ));
+            writeln!(circuit, "DETECTOR({}, {}, 0) {}", i, j, records.join(" ")).unwrap();
+        }
+        for (observable_idx, observable) in self.logical_observables.iter().enumerate() {
+            if observable.is_empty()
+                || !observable.iter().all(|(_, check_type)| check_type == basis)
+            {
+                continue;
+            }
+            let records: Vec<String> = observable
+                .iter()
+                .map(|(data_index, _)| data_lookback(*data_index))
+                .collect();
+            writeln!(
+                circuit,
+                "OBSERVABLE_INCLUDE({}) {}",
+                observable_idx,
+                records.join(" ")
+            )
+            .unwrap();
+        }
+        Ok(circuit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codes::*;
+    use crate::stabilizer::StabilizerCode;
+
+    #[test]
+    fn test_stim_memory_circuit() {
+        // cargo test -- test_stim_memory_circuit --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        let circuit = code.client_info.stim_memory_circuit(0.001, 3, "Z").unwrap();
+        println!("{}", circuit);
+        // 4 Z stabilizers in the first round, 8 stabilizers in the repeated rounds and 4 Z stabilizers at the end
+        assert_eq!(circuit.matches("DETECTOR").count(), 4 + 8 + 4);
+        assert_eq!(circuit.matches("OBSERVABLE_INCLUDE(0)").count(), 1);
+        assert_eq!(circuit.matches("OBSERVABLE_INCLUDE(1)").count(), 0);
+        assert!(circuit.contains("REPEAT 2 {"));
+        assert!(code.client_info.stim_memory_circuit(0.001, 0, "Z").is_err());
+    }
+
+    #[test]
+    fn test_stim_commuting_schedule() {
+        // cargo test -- test_stim_commuting_schedule --nocapture
+        let mut codes = vec![];
+        for d in [3, 5, 7] {
+            for noise_type in [NoiseType::Depolarize, NoiseType::BitFlip, NoiseType::OnlyY] {
+                codes.push(ServerCodeInfo::from(&RotatedSurfaceCode::new(
+                    d, noise_type,
+                )));
+            }
+            codes.push(ServerCodeInfo::from(&TriangularColorCodeBitFlip::new(d)));
+        }
+        for code in codes.iter() {
+            let layers = code.client_info.stim_gate_layers();
+            let gates: Vec<(usize, usize, String)> = layers.iter().flatten().cloned().collect();
+            assert!(code.client_info.stim_is_commuting_order(&gates));
+            let gate_num: usize = code
+                .client_info
+                .stabilizer_checks
+                .iter()
+                .map(|check| check.len())
+                .sum();
+            assert_eq!(gates.len(), gate_num);
+        }
+        // the rotated surface code measures all the stabilizers in the 4 layers of the hook-aware order
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::Depolarize));
+        assert!(code.client_info.stim_geometric_steps().is_some());
+        assert_eq!(code.client_info.stim_gate_layers().len(), 4);
+        // a code with mixed checks falls back to a commuting order
+        let code = StabilizerCode {
+            stabilizers: ["XZZXI", "IXZZX", "XIXZZ", "ZXIXZ"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+        .build("five-qubit")
+        .unwrap();
+        assert!(code.client_info.stim_geometric_steps().is_none());
+        let gates: Vec<(usize, usize, String)> = code
+            .client_info
+            .stim_gate_layers()
+            .into_iter()
+            .flatten()
+            .collect();
+        assert!(code.client_info.stim_is_commuting_order(&gates));
+    }
+}
@@ -0,0 +1,403 @@
+use crate::codes::*;
+use serde::Serialize;
+
+/// do not fill the logical observables of codes larger than this, as the elimination is O(n^3)
+const MAX_FILLED_DATA_QUBITS: usize = 2000;
+/// do not fill more logical observables than this, e.g. for large classical codes
+const MAX_FILLED_LOGICAL_QUBITS: usize = 100;
+
+/// a Pauli operator on n qubits as a vector over GF(2)^(2n) ignoring the phase, where coordinate i < n
+/// is the Z component on qubit i and coordinate n + i is the X component on qubit i
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymplecticVector {
+    n: usize,
+    z: Vec<u64>,
+    x: Vec<u64>,
+}
+
+impl SymplecticVector {
+    pub fn new(n: usize) -> Self {
+        let words = n.div_ceil(64);
+        Self {
+            n,
+            z: vec![0; words],
+            x: vec![0; words],
+        }
+    }
+
+    /// the product of the single-qubit Pauli terms; terms other than X, Y and Z are ignored
+    pub fn from_operator(n: usize, operator: &[(usize, String)]) -> Self {
+        let mut vector = Self::new(n);
+        for (data_index, pauli) in operator.iter() {
+            let (has_x, has_z) = match pauli.as_str() {
+                "X" => (true, false),
+                "Y" => (true, true),
+                "Z" => (false, true),
+                _ => (false, false),
+            };
+            if has_z {
+                vector.flip(*data_index);
+            }
+            if has_x {
+                vector.flip(n + *data_index);
+            }
+        }
+        vector
+    }
+
+    /// the single-qubit Pauli terms in the order of the qubits
+    pub fn to_operator(&self) -> Vec<(usize, String)> {
+        (0..self.n)
+            .filter_map(
+                |data_index| match (self.get(self.n + data_index), self.get(data_index)) {
+                    (true, false) => Some((data_index, "X".to_string())),
+                    (true, true) => Some((data_index, "Y".to_string())),
+                    (false, true) => Some((data_index, "Z".to_string())),
+                    (false, false) => None,
+                },
+            )
+            .collect()
+    }
+
+    fn words(&self, coordinate: usize) -> (&[u64], usize) {
+        if coordinate < self.n {
+            (&self.z, coordinate)
+        } else {
+            (&self.x, coordinate - self.n)
+        }
+    }
+
+    pub fn get(&self, coordinate: usize) -> bool {
+        let (words, bit) = self.words(coordinate);
+        (words[bit / 64] >> (bit % 64)) & 1 == 1
+    }
+
+    pub fn flip(&mut self, coordinate: usize) {
+        let (words, bit) = if coordinate < self.n {
+            (&mut self.z, coordinate)
+        } else {
+            (&mut self.x, coordinate - self.n)
+        };
+        words[bit / 64] ^= 1u64 << (bit % 64);
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.z.iter().chain(self.x.iter()).all(|&word| word == 0)
+    }
+
+    /// the first non-zero coordinate, so that the Z components come before the X components
+    pub fn pivot(&self) -> Option<usize> {
+        let first = |words: &[u64]| {
+            words
+                .iter()
+                .enumerate()
+                .find(|(_, word)| **word != 0)
+                .map(|(idx, word)| idx * 64 + word.trailing_zeros() as usize)
+        };
+        first(&self.z).or_else(|| first(&self.x).map(|bit| self.n + bit))
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        for (word, other_word) in self.z.iter_mut().zip(other.z.iter()) {
+            *word ^= other_word;
+        }
+        for (word, other_word) in self.x.iter_mut().zip(other.x.iter()) {
+            *word ^= other_word;
+        }
+    }
+
+    /// whether the two operators anticommute, i.e. their symplectic inner product is 1
+    pub fn anticommutes(&self, other: &Self) -> bool {
+        let mut parity = 0;
+        for idx in 0..self.z.len() {
+            parity ^= (self.z[idx] & other.x[idx]).count_ones();
+            parity ^= (self.x[idx] & other.z[idx]).count_ones();
+        }
+        parity & 1 == 1
+    }
+
+    /// exchange the Z and X components, so that the dot product with the result is the symplectic inner product
+    fn swapped(&self) -> Self {
+        Self {
+            n: self.n,
+            z: self.x.clone(),
+            x: self.z.clone(),
+        }
+    }
+}
+
+/// a basis of a subspace in reduced row echelon form, i.e. each row is zero at the pivots of the other rows
+#[derive(Debug, Clone, Default)]
+pub struct EchelonBasis {
+    rows: Vec<(usize, SymplecticVector)>, // (pivot, row) sorted by the pivot
+}
+
+impl EchelonBasis {
+    pub fn rank(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &SymplecticVector> {
+        self.rows.iter().map(|(_, row)| row)
+    }
+
+    /// the unique representative of the coset of the span, which is zero at all the pivots
+    pub fn reduce(&self, mut vector: SymplecticVector) -> SymplecticVector {
+        for (pivot, row) in self.rows.iter() {
+            if vector.get(*pivot) {
+                vector.add(row);
+            }
+        }
+        vector
+    }
+
+    /// add a vector to the basis, returning whether it is independent of the existing rows
+    pub fn insert(&mut self, vector: SymplecticVector) -> bool {
+        let vector = self.reduce(vector);
+        let Some(pivot) = vector.pivot() else {
+            return false;
+        };
+        for (_, row) in self.rows.iter_mut() {
+            if row.get(pivot) {
+                row.add(&vector);
+            }
+        }
+        let position = self.rows.partition_point(|(other, _)| *other < pivot);
+        self.rows.insert(position, (pivot, vector));
+        true
+    }
+
+    /// a basis of the vectors whose symplectic inner products with all the rows are 0
+    pub fn symplectic_complement(&self, n: usize) -> Vec<SymplecticVector> {
+        let mut equations = EchelonBasis::default();
+        for row in self.rows() {
+            equations.insert(row.swapped());
+        }
+        let mut is_pivot = vec![false; 2 * n];
+        for (pivot, _) in equations.rows.iter() {
+            is_pivot[*pivot] = true;
+        }
+        (0..2 * n)
+            .filter(|coordinate| !is_pivot[*coordinate])
+            .map(|free| {
+                // set the free coordinate to 1 and solve each equation for its pivot
+                let mut solution = SymplecticVector::new(n);
+                solution.flip(free);
+                for (pivot, equation) in equations.rows.iter() {
+                    if equation.get(free) {
+                        solution.flip(*pivot);
+                    }
+                }
+                solution
+            })
+            .collect()
+    }
+}
+
+/// the logical operators of a stabilizer code, as conjugate pairs where the i-th Z logical anticommutes
+/// with the i-th X logical and commutes with all the other logical operators
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LogicalBasis {
+    /// the number of independent stabilizers
+    pub stabilizer_rank: usize,
+    /// the number of logical qubits, k = n - rank
+    pub k: usize,
+    pub z_logicals: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+    pub x_logicals: Vec<Vec<(usize, String)>>, // (data_qubit_index, check_type)
+}
+
+impl LogicalBasis {
+    /// the logical observables in the order of Z0, X0, Z1, X1, ...
+    pub fn observables(&self) -> Vec<Vec<(usize, String)>> {
+        self.z_logicals
+            .iter()
+            .zip(self.x_logicals.iter())
+            .flat_map(|(z_logical, x_logical)| [z_logical.clone(), x_logical.clone()])
+            .collect()
+    }
+}
+
+/// the independent stabilizers in reduced row echelon form, or an error if the checks do not commute
+pub fn stabilizer_basis(
+    data_qubit_num: usize,
+    stabilizer_checks: &[Vec<(usize, String)>],
+) -> Result<EchelonBasis, String> {
+    let mut stabilizers = EchelonBasis::default();
+    for (stabilizer_idx, check) in stabilizer_checks.iter().enumerate() {
+        if let Some((out_of_range, _)) = check.iter().find(|(idx, _)| *idx >= data_qubit_num) {
+            return Err(format!(
+                "stabilizer check {} acts on data qubit {} out of range",
+                stabilizer_idx, out_of_range
+            ));
+        }
+        stabilizers.insert(SymplecticVector::from_operator(data_qubit_num, check));
+    }
+    let rows: Vec<&SymplecticVector> = stabilizers.rows().collect();
+    for (a, row) in rows.iter().enumerate() {
+        if rows[a + 1..].iter().any(|other| row.anticommutes(other)) {
+            return Err("the stabilizer checks do not commute".to_string());
+        }
+    }
+    Ok(stabilizers)
+}
+
+/// compute k and a canonical basis of logical operators from the stabilizer checks: the normalizer is reduced
+/// modulo the stabilizers into echelon form and then paired up by the symplectic Gram-Schmidt process;
+/// for CSS codes, the Z logicals are of pure Z type and the X logicals are of pure X type
+pub fn logical_basis(
+    data_qubit_num: usize,
+    stabilizer_checks: &[Vec<(usize, String)>],
+) -> Result<LogicalBasis, String> {
+    let stabilizers = stabilizer_basis(data_qubit_num, stabilizer_checks)?;
+    let mut quotient = EchelonBasis::default();
+    for vector in stabilizers.symplectic_complement(data_qubit_num) {
+        quotient.insert(stabilizers.reduce(vector));
+    }
+    let mut candidates: Vec<SymplecticVector> = quotient.rows().cloned().collect();
+    let mut basis = LogicalBasis {
+        stabilizer_rank: stabilizers.rank(),
+        k: data_qubit_num - stabilizers.rank(),
+        ..Default::default()
+    };
+    while !candidates.is_empty() {
+        let z_logical = candidates.remove(0);
+        let Some(position) = candidates
+            .iter()
+            .position(|candidate| z_logical.anticommutes(candidate))
+        else {
+            // an element of the normalizer commuting with the whole normalizer must be a stabilizer
+            unreachable!("a non-trivial logical operator has no conjugate");
+        };
+        let x_logical = candidates.remove(position);
+        for candidate in candidates.iter_mut() {
+            let (anticommutes_z, anticommutes_x) = (
+                candidate.anticommutes(&z_logical),
+                candidate.anticommutes(&x_logical),
+            );
+            if anticommutes_x {
+                candidate.add(&z_logical);
+            }
+            if anticommutes_z {
+                candidate.add(&x_logical);
+            }
+        }
+        basis.z_logicals.push(z_logical.to_operator());
+        basis.x_logicals.push(x_logical.to_operator());
+    }
+    debug_assert_eq!(basis.z_logicals.len(), basis.k);
+    Ok(basis)
+}
+
+impl ClientCodeInfo {
+    pub fn logical_basis(&self) -> Result<LogicalBasis, String> {
+        logical_basis(self.data_qubit_actions.len(), &self.stabilizer_checks)
+    }
+
+    /// fill in the logical observables from the computed basis if none is given; they are left empty for
+    /// codes too large to have them computed or listed, and for invalid codes which are reported by `validate`
+    pub fn fill_logical_observables(&mut self) {
+        if !self.logical_observables.is_empty()
+            || self.data_qubit_actions.len() > MAX_FILLED_DATA_QUBITS
+        {
+            return;
+        }
+        let Ok(basis) = self.logical_basis() else {
+            return;
+        };
+        if basis.k <= MAX_FILLED_LOGICAL_QUBITS {
+            self.logical_observables = basis.observables();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parity_check::*;
+    use crate::stabilizer::*;
+
+    fn assert_conjugate_pairs(n: usize, checks: &[Vec<(usize, String)>], basis: &LogicalBasis) {
+        let stabilizers: Vec<SymplecticVector> = checks
+            .iter()
+            .map(|check| SymplecticVector::from_operator(n, check))
+            .collect();
+        let observables: Vec<SymplecticVector> = basis
+            .observables()
+            .iter()
+            .map(|observable| SymplecticVector::from_operator(n, observable))
+            .collect();
+        for observable in observables.iter() {
+            assert!(!observable.is_zero());
+            for stabilizer in stabilizers.iter() {
+                assert!(!observable.anticommutes(stabilizer));
+            }
+        }
+        for (a, observable) in observables.iter().enumerate() {
+            for (b, other) in observables.iter().enumerate() {
+                let is_conjugate = a != b && a / 2 == b / 2;
+                assert_eq!(observable.anticommutes(other), is_conjugate);
+            }
+        }
+    }
+
+    #[test]
+    fn test_logical_basis_five_qubit_code() {
+        // cargo test -- test_logical_basis_five_qubit_code --nocapture
+        let client_info = StabilizerCode {
+            stabilizers: ["XZZXI", "IXZZX", "XIXZZ", "ZXIXZ"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }
+        .build_client_info("five")
+        .unwrap();
+        let basis = client_info.logical_basis().unwrap();
+        println!("{:?}", basis);
+        assert_eq!(basis.stabilizer_rank, 4);
+        assert_eq!(basis.k, 1);
+        assert_conjugate_pairs(5, &client_info.stabilizer_checks, &basis);
+        // the uploaded code is filled with the computed logical observables
+        assert_eq!(client_info.logical_observables, basis.observables());
+    }
+
+    #[test]
+    fn test_logical_basis_css_codes() {
+        // cargo test -- test_logical_basis_css_codes --nocapture
+        for d in [3, 5] {
+            let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(d, NoiseType::Depolarize));
+            let basis = code.client_info.logical_basis().unwrap();
+            assert_eq!(basis.k, 1);
+            assert!(basis.z_logicals[0].iter().all(|(_, pauli)| pauli == "Z"));
+            assert!(basis.x_logicals[0].iter().all(|(_, pauli)| pauli == "X"));
+            assert_conjugate_pairs(d * d, &code.client_info.stabilizer_checks, &basis);
+            let code = ServerCodeInfo::from(&TriangularColorCodeBitFlip::new(d));
+            assert_eq!(code.client_info.logical_observables.len(), 2);
+        }
+        // a classical repetition code has one logical bit
+        let client_info = ParityCheckCode {
+            h: Some(BinaryMatrix::Sparse {
+                columns: 5,
+                rows: vec![vec![0, 1], vec![1, 2], vec![2, 3], vec![3, 4], vec![0, 4]],
+            }),
+            ..Default::default()
+        }
+        .build_client_info("repetition")
+        .unwrap();
+        let basis = client_info.logical_basis().unwrap();
+        assert_eq!((basis.stabilizer_rank, basis.k), (4, 1));
+        assert_eq!(basis.x_logicals[0].len(), 5);
+        assert_conjugate_pairs(5, &client_info.stabilizer_checks, &basis);
+    }
+
+    #[test]
+    fn test_logical_basis_invalid() {
+        // cargo test -- test_logical_basis_invalid --nocapture
+        let checks = vec![
+            vec![(0, "Z".to_string()), (1, "Z".to_string())],
+            vec![(1, "X".to_string()), (2, "X".to_string())],
+        ];
+        assert!(logical_basis(3, &checks).is_err());
+        assert!(logical_basis(1, &checks).is_err());
+    }
+}
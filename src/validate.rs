@@ -0,0 +1,315 @@
+use crate::codes::*;
+use num_traits::cast::ToPrimitive;
+use std::collections::{BTreeSet, HashMap};
+
+/// stop collecting issues after this many
+const MAX_ISSUES: usize = 20;
+
+/// the parity of the number of anticommuting positions between each pair of operators that overlap,
+/// keyed by (index in `left`, index in `right`)
+fn anticommuting_pairs(
+    left: &[Vec<(usize, String)>],
+    right: &[Vec<(usize, String)>],
+) -> HashMap<(usize, usize), bool> {
+    let mut right_on_qubit: HashMap<usize, Vec<(usize, &str)>> = HashMap::new();
+    for (right_idx, operator) in right.iter().enumerate() {
+        for (data_index, pauli) in operator.iter() {
+            right_on_qubit
+                .entry(*data_index)
+                .or_default()
+                .push((right_idx, pauli.as_str()));
+        }
+    }
+    let mut parities = HashMap::new();
+    for (left_idx, operator) in left.iter().enumerate() {
+        for (data_index, pauli) in operator.iter() {
+            for &(right_idx, right_pauli) in right_on_qubit.get(data_index).into_iter().flatten() {
+                if pauli_anticommute(pauli, right_pauli) {
+                    *parities.entry((left_idx, right_idx)).or_insert(false) ^= true;
+                }
+            }
+        }
+    }
+    parities
+}
+
+impl ClientCodeInfo {
+    /// check the invariants of the code:
+    /// the indices are in range and the shapes are non-empty, the stabilizers commute with each other,
+    /// `data_qubit_actions` agree with `stabilizer_checks`, and the logical observables commute with all
+    /// the stabilizers; when some logical observables anticommute (unlike the detector error models whose
+    /// observables are all measured in one basis), they must come in conjugate pairs (0, 1), (2, 3), ...
+    /// that anticommute within a pair and commute across pairs
+    pub fn validate(&self) -> Result<(), String> {
+        self.check_consistency()?;
+        let mut issues = vec![];
+        for (stabilizer_idx, shape) in self.stabilizer_shapes.iter().enumerate() {
+            if shape.is_empty() {
+                issues.push(format!("stabilizer {} has an empty shape", stabilizer_idx));
+            }
+        }
+        for (&(a, b), &is_anticommuting) in
+            anticommuting_pairs(&self.stabilizer_checks, &self.stabilizer_checks).iter()
+        {
+            if a < b && is_anticommuting {
+                issues.push(format!("stabilizers {} and {} anticommute", a, b));
+            }
+        }
+        let mut expected_syndromes: Vec<HashMap<&str, BTreeSet<usize>>> = self
+            .data_qubit_actions
+            .iter()
+            .map(|actions| {
+                actions
+                    .keys()
+                    .map(|error_type| (error_type.as_str(), BTreeSet::new()))
+                    .collect()
+            })
+            .collect();
+        for (stabilizer_idx, check) in self.stabilizer_checks.iter().enumerate() {
+            for (data_index, check_type) in check.iter() {
+                for (error_type, expected) in expected_syndromes[*data_index].iter_mut() {
+                    if pauli_anticommute(error_type, check_type) {
+                        expected.insert(stabilizer_idx);
+                    }
+                }
+            }
+        }
+        for (data_index, actions) in self.data_qubit_actions.iter().enumerate() {
+            for (error_type, syndrome) in actions.iter() {
+                let expected = &expected_syndromes[data_index][error_type.as_str()];
+                let actual: BTreeSet<usize> = syndrome.iter().cloned().collect();
+                if *expected != actual || actual.len() != syndrome.len() {
+                    issues.push(format!(
+                        "{} error on data qubit {} has syndrome {:?} but the stabilizer checks give {:?}",
+                        error_type, data_index, syndrome, expected
+                    ));
+                }
+            }
+        }
+        for (&(observable_idx, stabilizer_idx), &is_anticommuting) in
+            anticommuting_pairs(&self.logical_observables, &self.stabilizer_checks).iter()
+        {
+            if is_anticommuting {
+                issues.push(format!(
+                    "logical observable {} anticommutes with stabilizer {}",
+                    observable_idx, stabilizer_idx
+                ));
+            }
+        }
+        let observable_num = self.logical_observables.len();
+        let parities = anticommuting_pairs(&self.logical_observables, &self.logical_observables);
+        if parities.values().any(|&is_anticommuting| is_anticommuting) {
+            if observable_num % 2 == 1 {
+                issues.push(format!(
+                    "{} logical observables cannot form conjugate pairs",
+                    observable_num
+                ));
+            }
+            for a in 0..observable_num {
+                for b in (a + 1)..observable_num {
+                    let is_anticommuting = parities.get(&(a, b)).cloned().unwrap_or(false);
+                    let is_conjugate = a / 2 == b / 2;
+                    if is_anticommuting != is_conjugate {
+                        issues.push(format!(
+                            "logical observables {} and {} should {}",
+                            a,
+                            b,
+                            if is_conjugate {
+                                "anticommute"
+                            } else {
+                                "commute"
+                            }
+                        ));
+                    }
+                }
+            }
+        }
+        if issues.is_empty() {
+            return Ok(());
+        }
+        issues.sort();
+        issues.truncate(MAX_ISSUES);
+        Err(issues.join("; "))
+    }
+}
+
+impl ServerCodeInfo {
+    /// check the invariants of the code and that the decoding graph matches it
+    pub fn validate(&self) -> Result<(), String> {
+        self.client_info.validate()?;
+        let vertex_num = self.solver_initializer.vertex_num;
+        if vertex_num != self.client_info.stabilizer_positions.len() {
+            return Err(format!(
+                "the decoding graph has {} vertices for {} stabilizers",
+                vertex_num,
+                self.client_info.stabilizer_positions.len()
+            ));
+        }
+        if self.visualize_positions.len() != vertex_num {
+            return Err(format!(
+                "{} visualize positions for {} vertices",
+                self.visualize_positions.len(),
+                vertex_num
+            ));
+        }
+        if self.edge_errors.len() != self.solver_initializer.weighted_edges.len() {
+            return Err(format!(
+                "{} edge errors for {} hyperedges",
+                self.edge_errors.len(),
+                self.solver_initializer.weighted_edges.len()
+            ));
+        }
+        for (name, len) in [
+            (
+                "lists of equivalent errors",
+                self.edge_equivalent_errors.len(),
+            ),
+            (
+                "lists of correlated errors",
+                self.edge_correlated_errors.len(),
+            ),
+            ("edge events", self.edge_events.len()),
+        ] {
+            if len != self.solver_initializer.weighted_edges.len() {
+                return Err(format!(
+                    "{} {} for {} hyperedges",
+                    len,
+                    name,
+                    self.solver_initializer.weighted_edges.len()
+                ));
+            }
+        }
+        for (edge_index, (hyperedge, representative)) in self
+            .solver_initializer
+            .weighted_edges
+            .iter()
+            .zip(self.edge_errors.iter())
+            .enumerate()
+        {
+            if hyperedge.weight.to_f64().is_some_and(|weight| weight < 0.0) {
+                return Err(format!("hyperedge {} has a negative weight", edge_index));
+            }
+            let equivalent_errors = &self.edge_equivalent_errors[edge_index];
+            let correlated_errors = &self.edge_correlated_errors[edge_index];
+            let event = &self.edge_events[edge_index];
+            let is_representative_valid = if equivalent_errors.is_empty() {
+                event.first() == Some(representative)
+            } else {
+                equivalent_errors.contains(representative)
+            };
+            let is_event_valid = (event.len() == 1 && equivalent_errors.contains(&event[0]))
+                || correlated_errors.iter().any(|&error_idx| {
+                    self.client_info
+                        .correlated_errors
+                        .get(error_idx)
+                        .is_some_and(|error| error.paulis == *event)
+                });
+            if !is_representative_valid || !is_event_valid {
+                return Err(format!(
+                    "the representative of hyperedge {} is not among its equivalent errors",
+                    edge_index
+                ));
+            }
+            let vertices: BTreeSet<usize> = hyperedge.vertices.iter().cloned().collect();
+            for &error_idx in correlated_errors.iter() {
+                let syndrome = self
+                    .client_info
+                    .correlated_errors
+                    .get(error_idx)
+                    .map(|error| self.client_info.correlated_syndrome(error))
+                    .ok_or(format!(
+                        "hyperedge {} stands for a nonexistent correlated error {}",
+                        edge_index, error_idx
+                    ))?;
+                if vertices != syndrome {
+                    return Err(format!(
+                        "hyperedge {} has vertices {:?} but correlated error {} has syndrome {:?}",
+                        edge_index, hyperedge.vertices, error_idx, syndrome
+                    ));
+                }
+            }
+            for (data_index, error_type) in equivalent_errors.iter() {
+                let syndrome = self
+                    .client_info
+                    .data_qubit_actions
+                    .get(*data_index)
+                    .and_then(|actions| actions.get(error_type))
+                    .ok_or(format!(
+                        "hyperedge {} stands for a nonexistent {} error on data qubit {}",
+                        edge_index, error_type, data_index
+                    ))?;
+                if vertices != syndrome.iter().cloned().collect() {
+                    return Err(format!(
+                        "hyperedge {} has vertices {:?} but the {} error on data qubit {} has syndrome {:?}",
+                        edge_index, hyperedge.vertices, error_type, data_index, syndrome
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stabilizer::StabilizerCode;
+
+    #[test]
+    fn test_validate_builtin_codes() {
+        // cargo test -- test_validate_builtin_codes --nocapture
+        for d in [3, 5] {
+            for noise_type in [NoiseType::Depolarize, NoiseType::BitFlip, NoiseType::OnlyY] {
+                let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(d, noise_type));
+                code.validate().unwrap();
+            }
+            ServerCodeInfo::from(&TriangularColorCodeBitFlip::new(d))
+                .validate()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_validate_invalid_codes() {
+        // cargo test -- test_validate_invalid_codes --nocapture
+        let stabilizers = |stabilizers: &[&str], logicals: &[&str]| StabilizerCode {
+            stabilizers: stabilizers.iter().map(|s| s.to_string()).collect(),
+            logical_observables: logicals.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let valid = stabilizers(&["ZZI", "IZZ"], &["ZII", "XXX"]);
+        valid.build("valid").unwrap().validate().unwrap();
+        // commuting logical observables are not paired
+        stabilizers(&["ZZI", "IZZ"], &["ZII", "ZZZ"])
+            .build("valid")
+            .unwrap()
+            .validate()
+            .unwrap();
+        // anticommuting stabilizers
+        let code = stabilizers(&["ZZI", "IXX"], &[]).build("invalid").unwrap();
+        assert!(code.validate().unwrap_err().contains("anticommute"));
+        // logical observable anticommuting with a stabilizer
+        let code = stabilizers(&["ZZI", "IZZ"], &["XII"])
+            .build("invalid")
+            .unwrap();
+        assert!(code.validate().is_err());
+        // logical observables in the wrong order of conjugate pairs
+        let code = stabilizers(&["ZZI", "IZZ"], &["ZII", "ZZZ", "XXX", "ZZZ"])
+            .build("invalid")
+            .unwrap();
+        assert!(code.validate().is_err());
+        // actions disagree with the checks
+        let mut code = valid.build("invalid").unwrap();
+        code.client_info.data_qubit_actions[0].insert("X".to_string(), vec![1]);
+        assert!(code.client_info.validate().is_err());
+        // an error type other than a Pauli
+        let mut code = valid.build("invalid").unwrap();
+        code.client_info.data_qubit_actions[0].insert("Q".to_string(), vec![0]);
+        assert!(
+            code.client_info
+                .validate()
+                .unwrap_err()
+                .contains("error type")
+        );
+    }
+}
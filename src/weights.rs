@@ -0,0 +1,350 @@
+use crate::codes::*;
+use mwpf::util::{HyperEdge, SolverInitializer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// changes of the weights of a code for a single decoding, which keep the edge indices of the decoding hypergraph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeightOverrides {
+    /// the probability of some errors, in place of those in `error_probabilities` of the code
+    #[serde(default)]
+    pub error_probabilities: Vec<(usize, String, f64)>, // (data_qubit_index, error_type, probability)
+    /// the raw weights of some hyperedges, applied after the error probabilities
+    #[serde(default)]
+    pub edge_weights: Vec<(usize, f64)>, // (edge_index, weight)
+}
+
+impl WeightOverrides {
+    /// parse the overrides in the format of "4:X:0.01,5:Z:0.02" for the error probabilities
+    /// and "3:0.5,7:2.1" for the edge weights
+    pub fn parse(
+        error_probabilities: Option<&str>,
+        edge_weights: Option<&str>,
+    ) -> Result<Self, String> {
+        let mut overrides = Self::default();
+        for item in error_probabilities
+            .into_iter()
+            .flat_map(|list| list.split(','))
+        {
+            let invalid = || format!("invalid error probability {}, expect qubit:type:p", item);
+            let mut fields = item.split(':');
+            let (Some(data_index), Some(error_type), Some(probability), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            overrides.error_probabilities.push((
+                data_index.parse().map_err(|_| invalid())?,
+                error_type.to_string(),
+                probability.parse().map_err(|_| invalid())?,
+            ));
+        }
+        for item in edge_weights.into_iter().flat_map(|list| list.split(',')) {
+            let invalid = || format!("invalid edge weight {}, expect edge:weight", item);
+            let Some((edge_index, weight)) = item.split_once(':') else {
+                return Err(invalid());
+            };
+            overrides.edge_weights.push((
+                edge_index.parse().map_err(|_| invalid())?,
+                weight.parse().map_err(|_| invalid())?,
+            ));
+        }
+        Ok(overrides)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.error_probabilities.is_empty() && self.edge_weights.is_empty()
+    }
+}
+
+impl ServerCodeInfo {
+    /// the solver initializer with the overridden weights; a hyperedge with an overridden error probability is
+    /// reweighted by merging all its errors again, so an error of a tiny probability keeps its hyperedge
+    pub fn reweighted_initializer(
+        &self,
+        overrides: &WeightOverrides,
+    ) -> Result<SolverInitializer, String> {
+        let client_info = &self.client_info;
+        let mut initializer = self.solver_initializer.clone();
+        let mut probabilities: HashMap<(usize, &str), f64> = HashMap::new();
+        for (data_index, error_type, probability) in overrides.error_probabilities.iter() {
+            if !client_info
+                .data_qubit_actions
+                .get(*data_index)
+                .is_some_and(|actions| actions.contains_key(error_type))
+            {
+                return Err(format!(
+                    "no {} error on data qubit {} to override",
+                    error_type, data_index
+                ));
+            }
+            if probability.is_nan() || *probability <= 0.0 || *probability > 0.5 {
+                return Err(format!(
+                    "probability {} of {} error on data qubit {} is not in (0, 0.5]",
+                    probability, error_type, data_index
+                ));
+            }
+            probabilities.insert((*data_index, error_type.as_str()), *probability);
+        }
+        // an error left out of the graph, e.g. of zero probability or undetectable, cannot be reweighted
+        let mut unmatched = probabilities.clone();
+        for errors in self.edge_equivalent_errors.iter() {
+            for (data_index, error_type) in errors.iter() {
+                unmatched.remove(&(*data_index, error_type.as_str()));
+            }
+        }
+        if let Some((data_index, error_type)) = unmatched.into_keys().min() {
+            return Err(format!(
+                "{} error on data qubit {} has no hyperedge to override",
+                error_type, data_index
+            ));
+        }
+        if !probabilities.is_empty() {
+            for (edge_index, errors) in self.edge_equivalent_errors.iter().enumerate() {
+                if !errors.iter().any(|(data_index, error_type)| {
+                    probabilities.contains_key(&(*data_index, error_type.as_str()))
+                }) {
+                    continue;
+                }
+                let weight =
+                    merged_weight(
+                        errors
+                            .iter()
+                            .map(|(data_index, error_type)| {
+                                match probabilities.get(&(*data_index, error_type.as_str())) {
+                                    Some(probability) => ((1.0 - probability) / probability).ln(),
+                                    None => client_info.error_weight(*data_index, error_type),
+                                }
+                            })
+                            .chain(self.edge_correlated_errors[edge_index].iter().map(
+                                |&error_idx| {
+                                    let probability =
+                                        client_info.correlated_errors[error_idx].probability;
+                                    ((1.0 - probability) / probability).ln()
+                                },
+                            )),
+                    );
+                initializer.weighted_edges[edge_index].weight = weight.into();
+            }
+        }
+        for &(edge_index, weight) in overrides.edge_weights.iter() {
+            if edge_index >= initializer.weighted_edges.len() {
+                return Err(format!("hyperedge {} out of range", edge_index));
+            }
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!(
+                    "weight {} of hyperedge {} is not a finite non-negative number",
+                    weight, edge_index
+                ));
+            }
+            initializer.weighted_edges[edge_index].weight = weight.into();
+        }
+        Ok(initializer)
+    }
+}
+
+/// the log-likelihood ratios ln((1-q)/q) of a single round of stabilizer measurements, each being wrong with
+/// probability q, given either as confidences 1-q in [0.5, 1] or directly as non-negative log-likelihood ratios
+pub fn measurement_llrs(
+    confidences: Option<&[f64]>,
+    llrs: Option<&[f64]>,
+) -> Result<Option<Vec<f64>>, String> {
+    match (confidences, llrs) {
+        (Some(_), Some(_)) => {
+            Err("give either the measurement confidences or the LLRs, not both".to_string())
+        }
+        (Some(confidences), None) => confidences
+            .iter()
+            .map(|&confidence| {
+                if !(0.5..=1.0).contains(&confidence) {
+                    return Err(format!(
+                        "measurement confidence {} is not in [0.5, 1]",
+                        confidence
+                    ));
+                }
+                Ok((confidence / (1.0 - confidence)).ln())
+            })
+            .collect::<Result<Vec<f64>, String>>()
+            .map(Some),
+        (None, Some(llrs)) => {
+            if let Some(llr) = llrs.iter().find(|llr| llr.is_nan() || **llr < 0.0) {
+                return Err(format!("measurement LLR {} is negative", llr));
+            }
+            Ok(Some(llrs.to_vec()))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// parse the soft information of [`measurement_llrs`] in the format of "0.99,0.9,..."
+pub fn parse_measurement_llrs(
+    confidences: Option<&str>,
+    llrs: Option<&str>,
+) -> Result<Option<Vec<f64>>, String> {
+    let parse = |list: Option<&str>, name: &str| -> Result<Option<Vec<f64>>, String> {
+        list.map(|list| {
+            list.split(',')
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid {} {}", name, value))
+                })
+                .collect()
+        })
+        .transpose()
+    };
+    let confidences = parse(confidences, "measurement confidence")?;
+    let llrs = parse(llrs, "measurement LLR")?;
+    measurement_llrs(confidences.as_deref(), llrs.as_deref())
+}
+
+impl ServerCodeInfo {
+    /// add a hyperedge for the error of each soft stabilizer measurement in a single round, which flips only its
+    /// own vertex, with the log-likelihood ratio as its weight; a defect of low confidence is then cheaply
+    /// explained by a measurement error instead of its incident hyperedges. Returns the stabilizer of each added
+    /// hyperedge, which follow the hyperedges of the code; the measurements of LLR infinity have no error.
+    /// A detector error model is rejected, as its detectors compare measurements of several rounds
+    pub fn add_measurement_errors(
+        &self,
+        initializer: &mut SolverInitializer,
+        llrs: &[f64],
+    ) -> Result<Vec<usize>, String> {
+        if self.client_info.is_detector_error_model {
+            return Err(
+                "soft information is for a single round of stabilizer measurements, while the detector error model has its own measurement errors"
+                    .to_string(),
+            );
+        }
+        let stabilizer_num = self.client_info.stabilizer_positions.len();
+        if llrs.len() != stabilizer_num {
+            return Err(format!(
+                "{} soft measurements for {} stabilizers",
+                llrs.len(),
+                stabilizer_num
+            ));
+        }
+        let mut stabilizers = vec![];
+        for (stabilizer_idx, &llr) in llrs.iter().enumerate() {
+            if llr.is_infinite() {
+                continue;
+            }
+            initializer
+                .weighted_edges
+                .push(HyperEdge::new(vec![stabilizer_idx], llr.into()));
+            stabilizers.push(stabilizer_idx);
+        }
+        Ok(stabilizers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::cast::ToPrimitive;
+
+    #[test]
+    fn test_reweighted_initializer() {
+        // cargo test -- test_reweighted_initializer --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let weight_of = |initializer: &SolverInitializer, edge_index: usize| {
+            initializer.weighted_edges[edge_index]
+                .weight
+                .to_f64()
+                .unwrap()
+        };
+        let overrides = WeightOverrides::parse(Some("4:X:0.01"), Some("0:2.5")).unwrap();
+        let initializer = code.reweighted_initializer(&overrides).unwrap();
+        let center_edge = code
+            .edge_equivalent_errors
+            .iter()
+            .position(|errors| errors.contains(&(4, "X".to_string())))
+            .unwrap();
+        assert!((weight_of(&initializer, center_edge) - 99f64.ln()).abs() < 1e-9);
+        assert_eq!(weight_of(&initializer, 0), 2.5);
+        for edge_index in 1..initializer.weighted_edges.len() {
+            if edge_index != center_edge {
+                assert_eq!(
+                    weight_of(&initializer, edge_index),
+                    weight_of(&code.solver_initializer, edge_index)
+                );
+            }
+        }
+        // invalid overrides
+        for (error_probabilities, edge_weights) in [
+            (Some("4:Z:0.01"), None),
+            (Some("4:X:0.9"), None),
+            (Some("9:X:0.1"), None),
+            (None, Some("100:1")),
+            (None, Some("0:-1")),
+        ] {
+            let overrides = WeightOverrides::parse(error_probabilities, edge_weights).unwrap();
+            assert!(code.reweighted_initializer(&overrides).is_err());
+        }
+        // an error of zero probability has no hyperedge
+        let mut client_info = code.client_info.clone();
+        client_info.error_probabilities =
+            vec![HashMap::new(); client_info.data_qubit_actions.len()];
+        client_info.error_probabilities[4].insert("X".to_string(), 0.0);
+        let sparse_code = ServerCodeInfo::from_client_info(client_info);
+        let overrides = WeightOverrides::parse(Some("4:X:0.01"), None).unwrap();
+        assert!(
+            sparse_code
+                .reweighted_initializer(&overrides)
+                .unwrap_err()
+                .contains("no hyperedge")
+        );
+        assert!(WeightOverrides::parse(Some("4:X"), None).is_err());
+        assert!(WeightOverrides::parse(None, Some("0-1")).is_err());
+    }
+
+    #[test]
+    fn test_measurement_errors() {
+        // cargo test -- test_measurement_errors --nocapture
+        let code = ServerCodeInfo::from(&RotatedSurfaceCode::new(3, NoiseType::BitFlip));
+        let stabilizer_num = code.client_info.stabilizer_positions.len();
+        let confidences = vec!["0.99"; stabilizer_num].join(",");
+        let llrs = parse_measurement_llrs(Some(&confidences), None)
+            .unwrap()
+            .unwrap();
+        assert!((llrs[0] - 99f64.ln()).abs() < 1e-9);
+        let mut initializer = code.solver_initializer.clone();
+        let stabilizers = code
+            .add_measurement_errors(&mut initializer, &llrs)
+            .unwrap();
+        assert_eq!(stabilizers, (0..stabilizer_num).collect::<Vec<usize>>());
+        let edge_num = code.solver_initializer.weighted_edges.len();
+        assert_eq!(initializer.weighted_edges.len(), edge_num + stabilizer_num);
+        assert_eq!(initializer.weighted_edges[edge_num].vertices, vec![0]);
+        // perfect measurements have no error
+        let mut llrs = llrs;
+        llrs[1] = f64::INFINITY;
+        let mut initializer = code.solver_initializer.clone();
+        let stabilizers = code
+            .add_measurement_errors(&mut initializer, &llrs)
+            .unwrap();
+        assert!(!stabilizers.contains(&1));
+        assert!(
+            code.add_measurement_errors(&mut initializer, &llrs[1..])
+                .is_err()
+        );
+        // a detector error model has its measurement errors already
+        let dem = crate::dem::DetectorErrorModel::parse("error(0.1) D0 D1\nerror(0.1) D1")
+            .unwrap()
+            .build("dem", "dem");
+        let mut initializer = dem.solver_initializer.clone();
+        assert!(
+            dem.add_measurement_errors(&mut initializer, &[1.0, 1.0])
+                .is_err()
+        );
+        // invalid soft information
+        assert!(parse_measurement_llrs(Some("0.4"), None).is_err());
+        assert!(parse_measurement_llrs(None, Some("-1")).is_err());
+        assert!(parse_measurement_llrs(Some("0.9"), Some("1")).is_err());
+        assert_eq!(parse_measurement_llrs(None, None).unwrap(), None);
+        assert!(measurement_llrs(None, Some(&[f64::NAN])).is_err());
+        assert_eq!(
+            parse_measurement_llrs(Some("1"), None).unwrap(),
+            Some(vec![f64::INFINITY])
+        );
+    }
+}